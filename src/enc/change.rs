@@ -0,0 +1,35 @@
+//! Contains the `EncChange` enum describing the mutations of an encyclopedia.
+use enc::ling::{LingTag, NoUserTag};
+
+/// A change made to an `Encyclopedia`.
+///
+/// The changes are recorded into the change log of the encyclopedia when it's enabled, see
+/// `Encyclopedia::enable_change_log`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EncChange<U = NoUserTag> {
+    /// A new word was added.
+    WordAdded(String),
+    /// An existing word was replaced by a new word with the same name.
+    /// The differences in the tags are recorded as `TagAdded` and `TagRemoved` changes.
+    WordReplaced(String),
+    /// A word was removed.
+    WordRemoved(String),
+    /// A word was renamed from the first name to the second one.
+    WordRenamed(String, String),
+    /// A tag was added to an existing word.
+    TagAdded(String, LingTag<U>),
+    /// A tag was removed from an existing word.
+    TagRemoved(String, LingTag<U>),
+    /// A new tag group was added.
+    TagGroupAdded(String),
+    /// An existing tag group was replaced.
+    TagGroupReplaced(String),
+    /// A tag group was removed.
+    TagGroupRemoved(String),
+    /// A new grammatical category was added.
+    GrammCatAdded(String),
+    /// An existing grammatical category was replaced.
+    GrammCatReplaced(String),
+    /// A grammatical category was removed.
+    GrammCatRemoved(String),
+}
@@ -0,0 +1,178 @@
+//! Contains the translation coverage reports of the encyclopedias.
+use std::collections::HashSet;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::GenericWord;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::grammeme::Grammeme;
+
+/// A required form of a word that isn't translated.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct MissingForm {
+    pub word: String,
+    pub grammemes: Vec<Grammeme<'static>>,
+}
+
+/// The translation coverage of a locale.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LocaleCoverage {
+    pub locale: String,
+    /// The words without a translated name, sorted by name.
+    pub missing_names: Vec<String>,
+    /// The required forms without a translation, sorted by the word names.
+    pub missing_forms: Vec<MissingForm>,
+    /// The tag groups without a translation, sorted by name.
+    pub untranslated_groups: Vec<String>,
+    /// The amount of translated names, forms and tag groups.
+    pub translated: usize,
+    /// The amount of names, forms and tag groups that need a translation.
+    pub total: usize,
+}
+
+impl LocaleCoverage {
+    /// Returns the percentage of the translated names, forms and tag groups, or 100 if nothing
+    /// needs a translation.
+    pub fn percentage(&self) -> f64 {
+        percentage(self.translated, self.total)
+    }
+
+    /// Returns true if everything is translated.
+    pub fn is_complete(&self) -> bool {
+        self.translated == self.total
+    }
+}
+
+/// The translation coverage of the locales.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct CoverageReport {
+    /// The coverages in the order the locales were given.
+    pub locales: Vec<LocaleCoverage>,
+}
+
+impl CoverageReport {
+    /// Returns the coverage of the locale.
+    pub fn get(&self, locale: &str) -> Option<&LocaleCoverage> {
+        self.locales.iter().find(|c| c.locale == locale)
+    }
+
+    /// Returns the percentage of the translated names, forms and tag groups of all of the
+    /// locales, or 100 if nothing needs a translation.
+    pub fn percentage(&self) -> f64 {
+        let translated = self.locales.iter().map(|c| c.translated).sum();
+        let total = self.locales.iter().map(|c| c.total).sum();
+        percentage(translated, total)
+    }
+}
+
+/// The translation coverage.
+///
+/// The translations are stored in the `LingTag::CustomStr` tags with the keys made by
+/// `format::po::locale_key`, so the name of a word is translated into a locale with the key
+/// `fi` and it's forms with keys like `fi|number=plural`. The grammemes of a form can be in
+/// any order in the key.
+///
+/// Every word needs a translated name, and the words with a `LingTag::Type` tag need the forms
+/// required for their types, see `Encyclopedia::add_required_form`. A tag group needs a
+/// translation if it has a text in the source locale, which is a `LingTag::CustomStr` tag with
+/// the locale set with `Encyclopedia::set_source_locale` as the key, and it is translated with a
+/// tag with the locale as the key. No tag group needs a translation if no source locale is set.
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Returns the translation coverage of the locales.
+    pub fn coverage<T: AsRef<str>>(&self, locales: &[T]) -> CoverageReport {
+        let mut words: Vec<GenericWord<U>> = self.iter_words().collect();
+        words.sort_by(|a, b| a.get_name().cmp(&b.get_name()));
+        let source = self.get_source_locale();
+        let mut groups: Vec<(&str, &HashSet<LingTag<U>>)> = self.iter_tag_groups()
+            .filter(|g| source.map_or(false, |s| g.1.iter().any(|t| translates_form(t, s, &[]))))
+            .collect();
+        groups.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut report = CoverageReport::default();
+        for locale in locales {
+            let locale = locale.as_ref();
+            let mut coverage = LocaleCoverage {
+                locale: locale.to_owned(),
+                missing_names: Vec::new(),
+                missing_forms: Vec::new(),
+                untranslated_groups: Vec::new(),
+                translated: 0,
+                total: 0,
+            };
+
+            for word in &words {
+                let tags: Vec<&LingTag<U>> = word.get_tags().iter().map(|t| &**t).collect();
+                coverage.total += 1;
+                if word.get_custom_str(locale).is_some() {
+                    coverage.translated += 1;
+                } else {
+                    coverage.missing_names.push(word.get_name().into_owned());
+                }
+
+                let mut forms: Vec<&Vec<Grammeme<'static>>> = Vec::new();
+                for tag in &tags {
+                    if let LingTag::Type(ref word_type) = **tag {
+                        for form in self.get_required_forms(word_type) {
+                            if !forms.contains(&form) {
+                                forms.push(form);
+                            }
+                        }
+                    }
+                }
+                for form in forms {
+                    coverage.total += 1;
+                    if tags.iter().any(|t| translates_form(t, locale, form)) {
+                        coverage.translated += 1;
+                    } else {
+                        coverage.missing_forms.push(MissingForm {
+                            word: word.get_name().into_owned(),
+                            grammemes: form.clone(),
+                        });
+                    }
+                }
+            }
+
+            for &(name, tags) in &groups {
+                coverage.total += 1;
+                if tags.iter().any(|t| translates_form(t, locale, &[])) {
+                    coverage.translated += 1;
+                } else {
+                    coverage.untranslated_groups.push(name.to_owned());
+                }
+            }
+            report.locales.push(coverage);
+        }
+        report
+    }
+}
+
+fn percentage(translated: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        translated as f64 * 100.0 / total as f64
+    }
+}
+
+/// Returns true if the tag is the translation of the form with the grammemes into the locale.
+pub(crate) fn translates_form<U: UserTag>(tag: &LingTag<U>,
+                                          locale: &str,
+                                          grammemes: &[Grammeme])
+                                          -> bool {
+    let key = match *tag {
+        LingTag::CustomStr(ref key, Some(_)) => key,
+        _ => return false,
+    };
+    if grammemes.is_empty() {
+        return key == locale;
+    }
+    if !key.starts_with(locale) || !key[locale.len()..].starts_with('|') {
+        return false;
+    }
+    let mut listed: Vec<&str> = key[locale.len() + 1..].split(',').collect();
+    let mut required: Vec<String> = grammemes.iter()
+        .map(|g| format!("{}={}", g.get_category_name(), g.get_value()))
+        .collect();
+    listed.sort();
+    required.sort();
+    listed == required
+}
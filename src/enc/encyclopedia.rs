@@ -1,81 +1,1033 @@
 //! Contains the `Encyclopedia` struct and an iterator to it's words.
 use std::collections::{HashSet, HashMap, hash_map};
 use std::iter::Iterator;
+use std::{cmp, mem, slice, vec};
 use std::borrow::Cow;
-use std::ops::Deref;
 
 use enc::word::*;
-use enc::ling::LingTag;
+use enc::change::EncChange;
+use enc::store::{WordId, WordTable, StoredTag, Symbol};
+use enc::transaction::History;
+use enc::schema::TagSchema;
+use enc::provenance::Provenance;
+use enc::ling::{LingTag, UserTag, NoUserTag};
 use enc::ling::grammeme::{GrammCategory, Grammeme};
+use enc::ling::affix::AffixClass;
+use enc::ling::collation::SortOrder;
 use enc::ling::error::LingError;
 
 type LingResult<T> = Result<T, LingError>;
 
+/// A stored word.
+#[derive(Clone)]
+struct WordEntry<U> {
+    tags: HashSet<StoredTag<U>>,
+    /// The tags loaded into `LingTag`s, so the word can be read without loading them again.
+    loaded: HashSet<LingTag<U>>,
+    /// The revision the word was last changed at.
+    revision: u64,
+    /// The revision the word was added at, kept when the word is replaced.
+    added: u64,
+}
+
+/// A stored tag group.
+#[derive(Clone)]
+struct TagGroupEntry<U> {
+    tags: HashSet<StoredTag<U>>,
+    /// The tags loaded into `LingTag`s, see `WordEntry::loaded`.
+    loaded: HashSet<LingTag<U>>,
+    /// The number the tag group was added with, kept when the group is replaced or renamed.
+    added: u64,
+}
+
+/// An encyclopedia of words without user-defined tags, see `Word`.
+pub type Encyclopedia = GenericEncyclopedia<NoUserTag>;
+
 /// A word manager that stores information about the tags associated with words.
+///
+/// `U` is the type of the user-defined tags of the words, see `LingTag::User`.
 #[allow(dead_code)] // TODO
-pub struct Encyclopedia {
-    /// An association from word names into their tags.
-    word_map: HashMap<String, HashSet<LingTag>>,
-    /// The common shared tag groups
-    tag_groups: HashMap<String, HashSet<LingTag>>,
+pub struct GenericEncyclopedia<U: UserTag> {
+    /// The names of the words and their ids.
+    word_table: WordTable,
+    /// The stored words indexed by their ids.
+    words: Vec<Option<WordEntry<U>>>,
+    /// The amount of stored words.
+    word_count: usize,
+    /// The ids of the stored words with the revisions they were added at, sorted by the
+    /// revisions.
+    word_order: Vec<(u64, WordId)>,
+    /// The old names of renamed words and the ids they refer to.
+    aliases: HashMap<String, WordId>,
+    /// The common shared tag groups by their interned names
+    tag_groups: HashMap<Symbol, TagGroupEntry<U>>,
+    /// The amount of tag groups added, which numbers them in the order they were added.
+    tag_groups_added: u64,
     // TODO: optimize the default value into a raw pointer.
     /// All of the available grammatical categories.
     /// Map from category names into their values.
     /// The first value in the tuple is the default value if any.
     gramm_cats: HashMap<String, (Option<String>, HashSet<String>)>,
+    /// The names of the grammatical categories in the order they were added.
+    gramm_cat_order: Vec<String>,
+    /// The affix classes the words can be inflected with.
+    affix_classes: HashMap<String, AffixClass>,
+    /// The word types and the grammemes of the forms the words of the types must have.
+    required_forms: HashMap<String, Vec<Vec<Grammeme<'static>>>>,
+    /// True if the rendered texts are pseudo-localized.
+    pseudo_locale: bool,
+    /// The locale of the texts the translations are made from, if any.
+    source_locale: Option<String>,
+    /// The revision counter, incremented on every word change.
+    revision: u64,
+    /// The schema the words are checked against, if any.
+    schema: Option<TagSchema>,
+    /// The declared relation types and their inverse relation types.
+    relations: HashMap<String, Option<String>>,
+    /// The ids of the words that have a `LingTag::Relation` tag to each id.
+    relation_sources: HashMap<WordId, HashSet<WordId>>,
+    /// The recorded provenance of the tags of the words, if provenance tracking is enabled.
+    provenance: Option<HashMap<WordId, HashMap<StoredTag<U>, Provenance>>>,
+    /// The layer recorded into the provenance of the added tags.
+    layer: Option<String>,
+    /// The recorded changes, if the change log is enabled.
+    change_log: Option<Vec<EncChange<U>>>,
+    /// The committed transactions that can be undone and redone.
+    pub(crate) history: History<U>,
 }
 
 impl Encyclopedia {
     /// Creates a new empty encyclopedia.
+    ///
+    /// The encyclopedias with user-defined tags are created with `Encyclopedia::default`.
     pub fn new() -> Encyclopedia {
-        Encyclopedia {
-            word_map: HashMap::new(),
+        Encyclopedia::default()
+    }
+}
+
+impl<U: UserTag> Default for GenericEncyclopedia<U> {
+    fn default() -> Self {
+        GenericEncyclopedia {
+            word_table: WordTable::new(),
+            words: Vec::new(),
+            word_count: 0,
+            word_order: Vec::new(),
+            aliases: HashMap::new(),
             tag_groups: HashMap::new(),
+            tag_groups_added: 0,
             gramm_cats: HashMap::new(),
+            gramm_cat_order: Vec::new(),
+            affix_classes: HashMap::new(),
+            required_forms: HashMap::new(),
+            pseudo_locale: false,
+            source_locale: None,
+            revision: 0,
+            schema: None,
+            relations: HashMap::new(),
+            relation_sources: HashMap::new(),
+            provenance: None,
+            layer: None,
+            change_log: None,
+            history: History::new(),
         }
     }
+}
 
+impl<U: UserTag> GenericEncyclopedia<U> {
     // WORDS
 
-    /// Adds a new word to the encyclopedia's word map.
-    pub fn add_word(&mut self, word: Word) {
-        let entry = word.into_map_entry();
-        self.word_map.insert(entry.0, entry.1);
+    /// Adds a new word to the encyclopedia's word map, replacing any previous word with the same
+    /// name.
+    ///
+    /// The word isn't checked against the schema, the tag groups or the declared relations, so
+    /// `validate` has to be called after adding words this way. `add_checked_word` and
+    /// `Transaction::add_word` check the words first.
+    ///
+    /// Returns the id of the word.
+    pub fn add_word(&mut self, word: GenericWord<U>) -> WordId {
+        let (name, loaded) = word.into_map_entry();
+        let id = self.word_table.intern(&name);
+        let tags: HashSet<StoredTag<U>> =
+            loaded.iter().map(|t| StoredTag::store(t, &mut self.word_table)).collect();
+
+        if self.change_log.is_some() {
+            let changes = match self.entry(id) {
+                Some(old) => {
+                    let mut changes = vec![EncChange::WordReplaced(name.clone())];
+                    changes.extend(old.loaded
+                        .difference(&loaded)
+                        .map(|t| EncChange::TagRemoved(name.clone(), t.clone())));
+                    changes.extend(loaded.difference(&old.loaded)
+                        .map(|t| EncChange::TagAdded(name.clone(), t.clone())));
+                    changes
+                }
+                None => vec![EncChange::WordAdded(name.clone())],
+            };
+            self.log_changes(changes);
+        }
+
+        let default = self.default_provenance();
+        if let Some(ref mut provenance) = self.provenance {
+            provenance.insert(id, tags.iter().map(|t| (t.clone(), default.clone())).collect());
+        }
+
+        self.revision += 1;
+        let entry = WordEntry {
+            tags: tags,
+            loaded: loaded,
+            revision: self.revision,
+            added: self.entry(id).map_or(self.revision, |old| old.added),
+        };
+        self.set_entry(id, Some(entry));
+        id
     }
 
     /// Returns a word with the given name or `None` if no such word was found.
-    pub fn get_word<'a, T>(&'a self, name: T) -> Option<Word<'a>>
+    ///
+    /// If there is no word with the name but the name is an alias of a word, that word is
+    /// returned with it's real name.
+    pub fn get_word<'a, T>(&'a self, name: T) -> Option<GenericWord<'a, U>>
         where T: 'a + AsRef<str> + Into<Cow<'a, str>>
     {
-        match self.word_map.get(name.as_ref()) {
-            Some(&ref set) => Some(Word::new_from_collection(name.into(), set)),
-            None => None,
+        self.word_id(name.as_ref()).map(|id| self.make_word(id))
+    }
+
+    /// Returns the word with the given id or `None` if no such word is stored.
+    pub fn get_word_by_id(&self, id: WordId) -> Option<GenericWord<U>> {
+        self.entry(id).map(|_| self.make_word(id))
+    }
+
+    /// Returns the id of the stored word with the given name or alias, or `None` if no such word
+    /// was found.
+    pub fn word_id<T: AsRef<str>>(&self, name: T) -> Option<WordId> {
+        let name = name.as_ref();
+        match self.word_table.get(name) {
+            Some(id) if self.entry(id).is_some() => Some(id),
+            // the name might be an old name of a renamed word
+            _ => self.aliases.get(name).cloned().filter(|&id| self.entry(id).is_some()),
+        }
+    }
+
+    /// Returns the name that has the given id.
+    ///
+    /// Ids are also given to the names of words that are referred to by tags but aren't stored,
+    /// so this can return a name even if no such word is stored.
+    pub fn word_name(&self, id: WordId) -> Option<&str> {
+        if id.index() < self.word_table.len() {
+            Some(self.word_table.name(id))
+        } else {
+            None
         }
     }
 
     /// Removes the word with the given name.
-    pub fn remove_word<'a, U: 'a + AsRef<str>>(&'a mut self, name: U) {
-        self.word_map.remove(name.as_ref());
+    ///
+    /// Like with `get_word`, the name can be an alias of the word. The aliases of the word are
+    /// removed too.
+    pub fn remove_word<'a, T: 'a + AsRef<str>>(&'a mut self, name: T) {
+        if let Some(id) = self.word_id(name.as_ref()) {
+            if let Some(ref mut provenance) = self.provenance {
+                provenance.remove(&id);
+            }
+            self.aliases.retain(|_, alias| *alias != id);
+            if self.set_entry(id, None).is_some() {
+                let name = self.word_table.name(id).to_owned();
+                self.log_changes(vec![EncChange::WordRemoved(name)]);
+            }
+        }
+    }
+
+    /// Adds a tag to the stored word with the given name.
+    ///
+    /// The tag is checked with `check_tags` first. Like with `Word::add_tag`, a custom or value
+    /// tag replaces the tag of the word with the same key.
+    /// Returns false if the word already had the tag.
+    pub fn add_word_tag<T: AsRef<str>>(&mut self, name: T, tag: LingTag<U>) -> LingResult<bool> {
+        let id = try!(self.word_id_or_err(name.as_ref()));
+        try!(self.check_tags(Some(Cow::Borrowed(&tag))));
+
+        let mut word = self.make_word(id).into_owned();
+        if word.get_tags().contains(&tag) {
+            return Ok(false);
+        }
+        word.add_tag(tag.into());
+        self.set_word_tags(id, word.into_map_entry().1);
+        Ok(true)
+    }
+
+    /// Removes a tag from the stored word with the given name.
+    /// Returns false if the word didn't have the tag.
+    pub fn remove_word_tag<T>(&mut self, name: T, tag: &LingTag<U>) -> LingResult<bool>
+        where T: AsRef<str>
+    {
+        let id = try!(self.word_id_or_err(name.as_ref()));
+        let removed = match StoredTag::find(tag, &self.word_table) {
+            Some(stored) => {
+                if let Some(ref mut provenance) = self.provenance {
+                    provenance.get_mut(&id).map(|p| p.remove(&stored));
+                }
+                self.index_relations(id, false);
+                let removed = {
+                    let entry = self.entry_mut(id).unwrap();
+                    entry.loaded.remove(tag);
+                    entry.tags.remove(&stored)
+                };
+                self.index_relations(id, true);
+                removed
+            }
+            // the tag refers to an unknown word, so no word can have it
+            None => false,
+        };
+
+        if removed {
+            self.touch_word(id);
+            let name = self.word_table.name(id).to_owned();
+            self.log_changes(vec![EncChange::TagRemoved(name, tag.clone())]);
+        }
+        Ok(removed)
+    }
+
+    /// Modifies the tags of the stored word with the given name that match the predicate `pred`.
+    ///
+    /// Every matching tag is replaced with the tag returned by `f`, or removed if it returns
+    /// `None`. The new tags are checked with `check_tags` and if any of them is invalid the word
+    /// is left unchanged. The new tags are added like with `Word::add_tag`, so they replace the
+    /// other tags with the same keys.
+    /// Returns the amount of tags that matched the predicate.
+    pub fn modify_word_tags<T, P, F>(&mut self, name: T, pred: P, mut f: F) -> LingResult<usize>
+        where T: AsRef<str>,
+              P: Fn(&LingTag<U>) -> bool,
+              F: FnMut(&LingTag<U>) -> Option<LingTag<U>>
+    {
+        let id = try!(self.word_id_or_err(name.as_ref()));
+        let mut word = self.make_word(id).into_owned();
+        let old_tags: Vec<LingTag<U>> =
+            word.get_tags().iter().map(|t| t.clone().into_owned()).filter(|t| pred(t)).collect();
+        let new_tags: Vec<LingTag<U>> = old_tags.iter().filter_map(|t| f(t)).collect();
+        try!(self.check_tags(new_tags.iter().map(Into::into)));
+
+        for tag in &old_tags {
+            word.remove_tag(tag);
+        }
+        for tag in new_tags {
+            word.add_tag(tag.into());
+        }
+        self.set_word_tags(id, word.into_map_entry().1);
+        Ok(old_tags.len())
+    }
+
+    /// Renames a stored word.
+    ///
+    /// The word keeps it's id, so all of the `LingTag::Parent`, `LingTag::Synonym`,
+    /// `LingTag::Relation` and `LingTag::HasWords` tags referring to the word in the other words
+    /// and in the tag groups refer to it by the new name. Tags that referred to the new name
+    /// before the renaming refer to the renamed word too.
+    /// If `leave_alias` is true, the old name is left as an alias of the new one, so `get_word`
+    /// still finds the word by it's old name.
+    ///
+    /// Fails if there is no word named `old` or if a word named `new` already exists.
+    pub fn rename_word<T, V>(&mut self,
+                             old: T,
+                             new: V,
+                             leave_alias: bool)
+                             -> LingResult<RenameReport>
+        where T: AsRef<str>,
+              V: Into<String>
+    {
+        let old = old.as_ref();
+        let new = new.into();
+        let id = match self.word_table.get(old) {
+            Some(id) if self.entry(id).is_some() => id,
+            _ => return Err(LingError::NoSuchWord(old.to_owned())),
+        };
+        // the id of the new name if it's only referred to by tags
+        let dangling = match self.word_table.get(&new) {
+            Some(new_id) if self.entry(new_id).is_some() => return Err(LingError::WordExists(new)),
+            other => other,
+        };
+
+        self.log_changes(vec![EncChange::WordRenamed(old.to_owned(), new.clone())]);
+        let report = self.rewrite_refs(|tags| {
+            let refers = tags.iter().any(|t| {
+                t.refers_to(id) || dangling.map_or(false, |d| t.refers_to(d))
+            });
+            if !refers {
+                return None;
+            }
+            Some(tags.iter()
+                .map(|t| match dangling.and_then(|d| t.replace_ref(d, id)) {
+                    Some(t) => t,
+                    None => t.clone(),
+                })
+                .collect())
+        }, |words| words.rename(id, &new));
+
+        self.aliases.remove(&new);
+        if leave_alias {
+            self.aliases.insert(old.to_owned(), id);
+        }
+        self.touch_word(id);
+
+        Ok(RenameReport {
+            words: report.0,
+            tag_groups: report.1,
+            alias_left: leave_alias,
+        })
+    }
+
+    /// Returns the name of the word the given name refers to.
+    ///
+    /// This is the name itself if there is a word with that name, or the name of the word it is an
+    /// alias of. Returns `None` if no such word was found.
+    pub fn resolve_name<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        self.word_id(name).map(|id| self.word_table.name(id))
+    }
+
+    /// Removes an alias left by `rename_word`.
+    pub fn remove_alias<T: AsRef<str>>(&mut self, alias: T) {
+        self.aliases.remove(alias.as_ref());
+    }
+
+    /// Changes the tags of words and tag groups.
+    ///
+    /// The function `f` is called with the tags of every word and tag group and it returns the
+    /// new tags for those it changes. After that, `rename` is called to change the names of the
+    /// words. The changes are recorded as if the changed tags were removed and added again.
+    /// Returns the names of the changed words and tag groups in sorted order.
+    fn rewrite_refs<F, R>(&mut self, f: F, rename: R) -> (Vec<String>, Vec<String>)
+        where F: Fn(&HashSet<StoredTag<U>>) -> Option<HashSet<StoredTag<U>>>,
+              R: FnOnce(&mut WordTable)
+    {
+        let mut new_words = Vec::new();
+        for (index, entry) in self.words.iter().enumerate() {
+            if let Some(ref entry) = *entry {
+                if let Some(tags) = f(&entry.tags) {
+                    new_words.push((stored_id(index), entry.loaded.clone(), tags));
+                }
+            }
+        }
+        let mut new_groups = Vec::new();
+        for (&name, group) in &self.tag_groups {
+            if let Some(tags) = f(&group.tags) {
+                new_groups.push((name, tags));
+            }
+        }
+
+        rename(&mut self.word_table);
+
+        let mut changes = Vec::new();
+        let mut words = Vec::new();
+        for (id, old_tags, tags) in new_words {
+            // the names of the referred words might have changed
+            let new_tags = self.load_tags(&tags);
+            self.index_relations(id, false);
+            {
+                let entry = self.entry_mut(id).unwrap();
+                entry.tags = tags;
+                entry.loaded = new_tags.clone();
+            }
+            self.index_relations(id, true);
+            let name = self.word_table.name(id).to_owned();
+            changes.extend(old_tags.difference(&new_tags)
+                .map(|t| EncChange::TagRemoved(name.clone(), t.clone())));
+            changes.extend(new_tags.difference(&old_tags)
+                .map(|t| EncChange::TagAdded(name.clone(), t.clone())));
+            self.touch_word(id);
+            words.push(name);
+        }
+        let mut tag_groups = Vec::new();
+        for (sym, tags) in new_groups {
+            let name = self.word_table.resolve(sym).to_owned();
+            changes.push(EncChange::TagGroupReplaced(name.clone()));
+            let group = TagGroupEntry {
+                loaded: self.load_tags(&tags),
+                tags: tags,
+                added: self.tag_groups[&sym].added,
+            };
+            self.tag_groups.insert(sym, group);
+            tag_groups.push(name);
+        }
+        self.log_changes(changes);
+
+        words.sort();
+        tag_groups.sort();
+        (words, tag_groups)
+    }
+
+    /// Returns the revision the word with the given name was last changed at or `None` if no
+    /// such word was found.
+    ///
+    /// Every change to a word gives it a new revision that is greater than any previous one, so
+    /// comparing revisions tells if the word has changed since it was last read.
+    pub fn word_revision<T: AsRef<str>>(&self, name: T) -> Option<u64> {
+        self.word_id(name).and_then(|id| self.word_revision_by_id(id))
+    }
+
+    /// Returns the revision the word with the given id was last changed at, see `word_revision`.
+    pub fn word_revision_by_id(&self, id: WordId) -> Option<u64> {
+        self.entry(id).map(|e| e.revision)
     }
 
     /// Returns the amount of words stored.
     pub fn word_amount(&self) -> usize {
-        self.word_map.len()
+        self.word_count
+    }
+
+    /// Returns the amount of distinct strings stored for the names and tags of the words and tag
+    /// groups.
+    ///
+    /// Every string is interned only once no matter how many words and tags use it. The strings
+    /// are never freed, even when nothing uses them anymore. The tags are also kept loaded, so
+    /// reading the words doesn't allocate.
+    pub fn interned_amount(&self) -> usize {
+        self.word_table.symbol_amount()
+    }
+
+    /// Returns an iterator to the words in the order they were added in.
+    ///
+    /// A replaced word keeps it's place, and a removed word that is added again is placed last.
+    pub fn iter_words<'a>(&'a self) -> WordIter<'a, U> {
+        WordIter {
+            enc: self,
+            iter: self.word_order.iter(),
+        }
+    }
+
+    /// Returns an iterator to the words in the given order of their names.
+    ///
+    /// The words with equal names in the order are in the order of `iter_words`.
+    pub fn iter_words_ordered(&self, order: &SortOrder) -> vec::IntoIter<GenericWord<U>> {
+        let mut words: Vec<GenericWord<U>> = self.iter_words().collect();
+        words.sort_by_cached_key(|w| order.sort_key(&w.get_name()));
+        words.into_iter()
+    }
+
+    /// Checks that the word is valid to be stored in this encyclopedia.
+    ///
+    /// Every tag group used by the word with `LingTag::UseTagGroup` has to exist and every
+    /// relation type used with `LingTag::Relation` has to be declared.
+    /// If the encyclopedia has a schema, the custom tags of the word have to be declared in it
+    /// and the word has to have the keys required for it's types. The tags of the used tag
+    /// groups count as the word's own tags when checking the required keys.
+    pub fn check_word(&self, word: &GenericWord<U>) -> LingResult<()> {
+        try!(self.check_tags(word.iter()));
+        if let Some(ref schema) = self.schema {
+            let group_tags: Vec<&LingTag<U>> = word.get_tags()
+                .iter()
+                .filter_map(|t| match **t {
+                    LingTag::UseTagGroup(ref group) => self.get_tag_group(group),
+                    _ => None,
+                })
+                .flat_map(|tags| tags)
+                .collect();
+            let tags = word.get_tags().iter().map(|t| &**t).chain(group_tags);
+            try!(schema.check_required(tags));
+        }
+        Ok(())
+    }
+
+    /// Gives the word the default values of the schema for the keys it doesn't have, see
+    /// `TagSchema::apply_defaults`, and checks it with `check_word`.
+    pub fn apply_schema(&self, word: &mut GenericWord<U>) -> LingResult<()> {
+        if let Some(ref schema) = self.schema {
+            schema.apply_defaults(word);
+        }
+        self.check_word(word)
+    }
+
+    /// Adds a new word like `add_word` after completing and checking it with `apply_schema`.
+    ///
+    /// Returns the id of the word, or the reason the word isn't valid.
+    pub fn add_checked_word(&mut self, word: GenericWord<U>) -> LingResult<WordId> {
+        let mut word = word;
+        try!(self.apply_schema(&mut word));
+        Ok(self.add_word(word))
+    }
+
+    /// Checks every stored word with `check_word` and returns the names of the invalid words
+    /// with the reasons, sorted by the names.
+    ///
+    /// This has to be called after changing the schema or adding words without checking them,
+    /// like with `add_word`.
+    pub fn validate(&self) -> Vec<(String, LingError)> {
+        let mut errors: Vec<(String, LingError)> = self.iter_words()
+            .filter_map(|w| self.check_word(&w).err().map(|e| (w.get_name().into_owned(), e)))
+            .collect();
+        errors.sort_by(|a, b| a.0.cmp(&b.0));
+        errors
+    }
+
+    /// Checks that the tags are valid to be stored in this encyclopedia, see `check_word`.
+    ///
+    /// The keys required by the schema aren't checked, since the tags aren't a whole word.
+    pub fn check_tags<'a, I>(&self, tags: I) -> LingResult<()>
+        where I: IntoIterator<Item = Cow<'a, LingTag<U>>>
+    {
+        for tag in tags {
+            match *tag {
+                LingTag::UseTagGroup(ref group) if self.tag_group(group).is_none() => {
+                    return Err(LingError::NoSuchTagGroup(group.clone()));
+                }
+                LingTag::Relation(ref relation, _) if !self.relations.contains_key(relation) => {
+                    return Err(LingError::UnknownRelation(relation.clone()));
+                }
+                _ => (),
+            }
+            if let Some(ref schema) = self.schema {
+                try!(schema.check_tag(&tag));
+            }
+        }
+        Ok(())
+    }
+
+    fn entry(&self, id: WordId) -> Option<&WordEntry<U>> {
+        self.words.get(id.index()).and_then(Option::as_ref)
+    }
+
+    fn entry_mut(&mut self, id: WordId) -> Option<&mut WordEntry<U>> {
+        self.words.get_mut(id.index()).and_then(Option::as_mut)
+    }
+
+    /// Stores or removes the word with the given id and returns the old entry.
+    fn set_entry(&mut self, id: WordId, entry: Option<WordEntry<U>>) -> Option<WordEntry<U>> {
+        while self.words.len() <= id.index() {
+            self.words.push(None);
+        }
+        self.index_relations(id, false);
+        let old = mem::replace(&mut self.words[id.index()], entry);
+        self.index_relations(id, true);
+
+        let old_key = old.as_ref().map(|e| (e.added, id));
+        let new_key = self.words[id.index()].as_ref().map(|e| (e.added, id));
+        if old_key != new_key {
+            if let Some(key) = old_key {
+                if let Ok(i) = self.word_order.binary_search(&key) {
+                    self.word_order.remove(i);
+                }
+            }
+            if let Some(key) = new_key {
+                if let Err(i) = self.word_order.binary_search(&key) {
+                    self.word_order.insert(i, key);
+                }
+            }
+        }
+        if old.is_some() {
+            self.word_count -= 1;
+        }
+        if self.words[id.index()].is_some() {
+            self.word_count += 1;
+        }
+        old
+    }
+
+    /// Adds the stored word with the given id to or removes it from the sources of the words it
+    /// has relations to, see `relation_sources`.
+    fn index_relations(&mut self, id: WordId, add: bool) {
+        let targets: Vec<WordId> = match self.entry(id) {
+            Some(entry) => {
+                entry.tags
+                    .iter()
+                    .filter_map(|t| match *t {
+                        StoredTag::Relation(_, target) => Some(target),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            None => return,
+        };
+        for target in targets {
+            if add {
+                self.relation_sources.entry(target).or_insert_with(HashSet::new).insert(id);
+                continue;
+            }
+            let empty = match self.relation_sources.get_mut(&target) {
+                Some(sources) => {
+                    sources.remove(&id);
+                    sources.is_empty()
+                }
+                None => false,
+            };
+            if empty {
+                self.relation_sources.remove(&target);
+            }
+        }
+    }
+
+    /// Returns the ids of the stored words that have a `LingTag::Relation` tag to the word with
+    /// the given id or to one of it's aliases.
+    pub(crate) fn relation_sources(&self, id: WordId) -> HashSet<WordId> {
+        let aliases = self.aliases
+            .iter()
+            .filter(|&(_, &word)| word == id)
+            .filter_map(|(alias, _)| self.word_table.get(alias));
+        Some(id)
+            .into_iter()
+            .chain(aliases)
+            .filter_map(|target| self.relation_sources.get(&target))
+            .flat_map(|sources| sources.iter().cloned())
+            .collect()
     }
 
-    /// Returns an iterator to the words
-    pub fn iter_words<'a>(&'a self) -> WordIter<'a> {
-        WordIter { iter: self.word_map.iter() }
+    /// Returns the id of the stored word with the given name or an error.
+    fn word_id_or_err(&self, name: &str) -> LingResult<WordId> {
+        self.word_id(name).ok_or(LingError::NoSuchWord(name.to_owned()))
+    }
+
+    /// Creates a view to the stored word with the given id.
+    fn make_word(&self, id: WordId) -> GenericWord<U> {
+        let tags = &self.entry(id).unwrap().loaded;
+        GenericWord::from_set(self.word_table.name(id), tags.iter().map(Cow::Borrowed).collect())
+    }
+
+    fn load_tags(&self, tags: &HashSet<StoredTag<U>>) -> HashSet<LingTag<U>> {
+        tags.iter().map(|t| t.load(&self.word_table)).collect()
+    }
+
+    /// Replaces the tags of the stored word with the given id and records the changed tags.
+    fn set_word_tags(&mut self, id: WordId, new_tags: HashSet<LingTag<U>>) {
+        let changes: Vec<EncChange<U>> = {
+            let old_tags = &self.entry(id).unwrap().loaded;
+            let name = self.word_table.name(id);
+            old_tags.difference(&new_tags)
+                .map(|t| EncChange::TagRemoved(name.to_owned(), t.clone()))
+                .chain(new_tags.difference(old_tags)
+                    .map(|t| EncChange::TagAdded(name.to_owned(), t.clone())))
+                .collect()
+        };
+        if changes.is_empty() {
+            return;
+        }
+
+        let stored: HashSet<StoredTag<U>> =
+            new_tags.iter().map(|t| StoredTag::store(t, &mut self.word_table)).collect();
+        let default = self.default_provenance();
+        if let Some(ref mut provenance) = self.provenance {
+            let word_prov = provenance.entry(id).or_insert_with(HashMap::new);
+            word_prov.retain(|t, _| stored.contains(t));
+            for tag in &stored {
+                word_prov.entry(tag.clone()).or_insert_with(|| default.clone());
+            }
+        }
+        self.index_relations(id, false);
+        {
+            let entry = self.entry_mut(id).unwrap();
+            entry.tags = stored;
+            entry.loaded = new_tags;
+        }
+        self.index_relations(id, true);
+        self.touch_word(id);
+        self.log_changes(changes);
+    }
+
+    /// Gives the word a new revision.
+    fn touch_word(&mut self, id: WordId) {
+        self.revision += 1;
+        let revision = self.revision;
+        if let Some(entry) = self.entry_mut(id) {
+            entry.revision = revision;
+        }
     }
 
     // TAG GROUPS
-    // TODO
+
+    /// Adds a new tag group, replacing any previous group with the same name.
+    ///
+    /// The tags of a tag group are shared by all of the words that have a `LingTag::UseTagGroup`
+    /// tag with the group's name. If many custom or value tags have the same key, the last one of
+    /// them is kept like in `Word::new_from_collection`.
+    pub fn add_tag_group<T, I>(&mut self, name: T, tags: I)
+        where T: Into<String>,
+              I: IntoIterator<Item = LingTag<U>>
+    {
+        let name = name.into();
+        let sym = self.word_table.symbol(&name);
+        let change = if self.tag_groups.contains_key(&sym) {
+            EncChange::TagGroupReplaced(name)
+        } else {
+            EncChange::TagGroupAdded(name)
+        };
+        self.log_changes(vec![change]);
+        let loaded = GenericWord::new_from_collection("", tags).into_map_entry().1;
+        let added = match self.tag_groups.get(&sym) {
+            Some(old) => old.added,
+            None => {
+                self.tag_groups_added += 1;
+                self.tag_groups_added
+            }
+        };
+        let group = TagGroupEntry {
+            tags: loaded.iter().map(|t| StoredTag::store(t, &mut self.word_table)).collect(),
+            loaded: loaded,
+            added: added,
+        };
+        self.tag_groups.insert(sym, group);
+    }
+
+    /// Returns the tags of the tag group with the given name or `None` if no such group was
+    /// found.
+    pub fn get_tag_group<T: AsRef<str>>(&self, name: T) -> Option<&HashSet<LingTag<U>>> {
+        self.tag_group(name.as_ref()).map(|group| &group.loaded)
+    }
+
+    /// Removes the tag group with the given name.
+    ///
+    /// Fails with `LingError::TagGroupInUse` if a stored word or another tag group uses the group.
+    pub fn remove_tag_group<T: AsRef<str>>(&mut self, name: T) -> LingResult<()> {
+        let name = name.as_ref();
+        let sym = match self.word_table.find_symbol(name) {
+            Some(sym) if self.tag_groups.contains_key(&sym) => sym,
+            _ => return Ok(()),
+        };
+        let used = LingTag::UseTagGroup(name.to_owned());
+        let in_use = self.iter_words().any(|w| w.get_tags().contains(&used)) ||
+                     self.iter_tag_groups().any(|(n, tags)| n != name && tags.contains(&used));
+        if in_use {
+            return Err(LingError::TagGroupInUse(name.to_owned()));
+        }
+        self.tag_groups.remove(&sym);
+        self.log_changes(vec![EncChange::TagGroupRemoved(name.to_owned())]);
+        Ok(())
+    }
+
+    /// Renames a tag group and rewrites every `LingTag::UseTagGroup` reference to it in the words
+    /// and the other tag groups.
+    ///
+    /// Fails if there is no group named `old` or if a group named `new` already exists.
+    pub fn rename_tag_group<T, V>(&mut self, old: T, new: V) -> LingResult<RenameReport>
+        where T: AsRef<str>,
+              V: Into<String>
+    {
+        let old = old.as_ref();
+        let new = new.into();
+        if self.tag_group(&new).is_some() {
+            return Err(LingError::TagGroupExists(new));
+        }
+        let old_sym = match self.word_table.find_symbol(old) {
+            Some(sym) if self.tag_groups.contains_key(&sym) => sym,
+            _ => return Err(LingError::NoSuchTagGroup(old.to_owned())),
+        };
+        let new_sym = self.word_table.symbol(&new);
+        self.log_changes(vec![EncChange::TagGroupRemoved(old.to_owned()),
+                              EncChange::TagGroupAdded(new)]);
+        let tags = self.tag_groups.remove(&old_sym).unwrap();
+        self.tag_groups.insert(new_sym, tags);
+
+        let old_tag = StoredTag::UseTagGroup(old_sym);
+        let new_tag = StoredTag::UseTagGroup(new_sym);
+        let is_old = |t: &StoredTag<U>| *t == old_tag;
+        let (words, tag_groups) = self.rewrite_refs(|tags| if tags.iter().any(&is_old) {
+                                                        Some(tags.iter()
+                                                            .map(|t| if is_old(t) {
+                                                                new_tag.clone()
+                                                            } else {
+                                                                t.clone()
+                                                            })
+                                                            .collect())
+                                                    } else {
+                                                        None
+                                                    },
+                                                    |_| ());
+        Ok(RenameReport {
+            words: words,
+            tag_groups: tag_groups,
+            alias_left: false,
+        })
+    }
+
+    /// Returns the amount of tag groups stored.
+    pub fn tag_group_amount(&self) -> usize {
+        self.tag_groups.len()
+    }
+
+    /// Returns an iterator to the tag group names and their tags in an arbitrary order, see
+    /// `iter_tag_groups_ordered`.
+    pub fn iter_tag_groups<'a>(&'a self) -> TagGroupIter<'a, U> {
+        TagGroupIter {
+            table: &self.word_table,
+            iter: self.tag_groups.iter(),
+        }
+    }
+
+    /// Returns an iterator to the tag group names and their tags in the given order of the
+    /// names.
+    ///
+    /// A replaced or renamed tag group keeps it's place in the insertion order.
+    pub fn iter_tag_groups_ordered(&self,
+                                   order: &SortOrder)
+                                   -> vec::IntoIter<(&str, &HashSet<LingTag<U>>)> {
+        let mut groups: Vec<(u64, &str, &HashSet<LingTag<U>>)> = self.tag_groups
+            .iter()
+            .map(|(&sym, group)| (group.added, self.word_table.resolve(sym), &group.loaded))
+            .collect();
+        groups.sort_by_key(|g| g.0);
+        groups.sort_by_cached_key(|g| order.sort_key(g.1));
+        groups.into_iter().map(|g| (g.1, g.2)).collect::<Vec<_>>().into_iter()
+    }
+
+    /// Returns the stored tag group with the given name.
+    fn tag_group(&self, name: &str) -> Option<&TagGroupEntry<U>> {
+        self.word_table.find_symbol(name).and_then(|sym| self.tag_groups.get(&sym))
+    }
+
+    // SCHEMA
+
+    /// Sets the schema the words are checked against with `check_word`.
+    ///
+    /// The stored words aren't checked, `validate` can be used for that.
+    pub fn set_schema(&mut self, schema: TagSchema) {
+        self.schema = Some(schema);
+    }
+
+    /// Removes the schema, allowing any custom tags.
+    pub fn remove_schema(&mut self) -> Option<TagSchema> {
+        self.schema.take()
+    }
+
+    /// Returns the schema if one is set.
+    pub fn get_schema(&self) -> Option<&TagSchema> {
+        self.schema.as_ref()
+    }
+
+    // RELATIONS
+
+    /// Declares a relation type without an inverse, replacing any previous declaration of it.
+    pub fn declare_relation<T: Into<String>>(&mut self, name: T) {
+        let name = name.into();
+        self.unlink_inverse(&name);
+        self.relations.insert(name, None);
+    }
+
+    /// Declares two relation types that are the inverses of each other, like `has_part` and
+    /// `part_of`, replacing any previous declarations of them.
+    ///
+    /// A relation that is it's own inverse is symmetric, so `declare_inverse_relations("antonym",
+    /// "antonym")` makes every antonym relation go both ways. See `Encyclopedia::related`.
+    pub fn declare_inverse_relations<T, V>(&mut self, name: T, inverse: V)
+        where T: Into<String>,
+              V: Into<String>
+    {
+        let (name, inverse) = (name.into(), inverse.into());
+        self.unlink_inverse(&name);
+        self.unlink_inverse(&inverse);
+        self.relations.insert(name.clone(), Some(inverse.clone()));
+        self.relations.insert(inverse, Some(name));
+    }
+
+    /// Removes the declaration of the relation type.
+    /// The inverse relation type stays declared without an inverse.
+    pub fn undeclare_relation<T: AsRef<str>>(&mut self, name: T) {
+        self.unlink_inverse(name.as_ref());
+        self.relations.remove(name.as_ref());
+    }
+
+    /// Returns true if the relation type is declared.
+    pub fn is_relation_declared<T: AsRef<str>>(&self, name: T) -> bool {
+        self.relations.contains_key(name.as_ref())
+    }
+
+    /// Returns the inverse of the relation type, or `None` if it has no inverse or if it isn't
+    /// declared.
+    pub fn get_inverse_relation<T: AsRef<str>>(&self, name: T) -> Option<&str> {
+        self.relations.get(name.as_ref()).and_then(|i| i.as_ref()).map(|i| i as &str)
+    }
+
+    /// Removes the inverse of the relation type from the inverse's declaration.
+    fn unlink_inverse(&mut self, name: &str) {
+        let inverse = self.relations.get(name).and_then(|i| i.clone());
+        if let Some(inverse) = inverse {
+            if let Some(i) = self.relations.get_mut(&inverse) {
+                *i = None;
+            }
+        }
+    }
+
+    // PROVENANCE
+
+    /// Starts recording where the tags of the words are defined.
+    ///
+    /// Every tag added after this gets a `Provenance` with the current layer, see `set_layer`.
+    /// The loaders record the source files and lines with `set_tag_provenance`.
+    pub fn enable_provenance(&mut self) {
+        if self.provenance.is_none() {
+            self.provenance = Some(HashMap::new());
+        }
+    }
+
+    /// Stops recording the provenance of the tags and forgets the recorded provenance.
+    pub fn disable_provenance(&mut self) {
+        self.provenance = None;
+    }
+
+    /// Returns true if the provenance of the tags is being recorded.
+    pub fn is_provenance_enabled(&self) -> bool {
+        self.provenance.is_some()
+    }
+
+    /// Sets the layer of content recorded into the provenance of the tags added after this.
+    pub fn set_layer(&mut self, layer: Option<String>) {
+        self.layer = layer;
+    }
+
+    /// Returns the current layer of content.
+    pub fn get_layer(&self) -> Option<&str> {
+        self.layer.as_ref().map(|s| s as &str)
+    }
+
+    /// Sets the provenance of a tag of the stored word with the given name.
+    ///
+    /// Returns false if provenance tracking isn't enabled or if the word doesn't have the tag.
+    pub fn set_tag_provenance<T>(&mut self,
+                                 name: T,
+                                 tag: &LingTag<U>,
+                                 provenance: Provenance)
+                                 -> LingResult<bool>
+        where T: AsRef<str>
+    {
+        let id = try!(self.word_id_or_err(name.as_ref()));
+        let stored = match StoredTag::find(tag, &self.word_table) {
+            Some(ref stored) if self.entry(id).unwrap().tags.contains(stored) => stored.clone(),
+            _ => return Ok(false),
+        };
+        match self.provenance {
+            Some(ref mut map) => {
+                map.entry(id).or_insert_with(HashMap::new).insert(stored, provenance);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the provenance given to the added tags.
+    fn default_provenance(&self) -> Provenance {
+        Provenance { layer: self.layer.clone(), ..Provenance::default() }
+    }
+
+    /// Returns the recorded provenance of a tag of the stored word with the given name.
+    pub fn get_tag_provenance(&self, name: &str, tag: &LingTag<U>) -> Option<&Provenance> {
+        let id = match self.word_id(name) {
+            Some(id) => id,
+            None => return None,
+        };
+        match (&self.provenance, StoredTag::find(tag, &self.word_table)) {
+            (&Some(ref map), Some(stored)) => map.get(&id).and_then(|p| p.get(&stored)),
+            _ => None,
+        }
+    }
 
     // GRAMMATICAL CATEGORIES
 
     /// Adds a new grammatical category.
     pub fn add_gramm_cat(&mut self, categ: GrammCategory) {
         let entry = categ.into_map_entry();
+        let change = if self.gramm_cats.contains_key(&entry.0) {
+            EncChange::GrammCatReplaced(entry.0.clone())
+        } else {
+            EncChange::GrammCatAdded(entry.0.clone())
+        };
+        self.log_changes(vec![change]);
+        if !self.gramm_cats.contains_key(&entry.0) {
+            self.gramm_cat_order.push(entry.0.clone());
+        }
         self.gramm_cats.insert(entry.0, entry.1);
     }
 
@@ -89,14 +1041,34 @@ impl Encyclopedia {
         }
     }
 
+    /// Removes the grammatical category with the given name.
+    pub fn remove_gramm_cat<T: AsRef<str>>(&mut self, name: T) {
+        if self.gramm_cats.remove(name.as_ref()).is_some() {
+            self.gramm_cat_order.retain(|n| n != name.as_ref());
+            self.log_changes(vec![EncChange::GrammCatRemoved(name.as_ref().to_owned())]);
+        }
+    }
+
     /// Returns the amount of stored grammatical categories.
     pub fn gramm_cat_amount(&self) -> usize {
         self.gramm_cats.len()
     }
 
-    /// Returns an iterator to the grammatical categories.
+    /// Returns an iterator to the grammatical categories in the order they were added.
     pub fn iter_gramm_cats<'a>(&'a self) -> GrammCatIter<'a> {
-        GrammCatIter { iter: self.gramm_cats.iter() }
+        GrammCatIter {
+            gramm_cats: &self.gramm_cats,
+            iter: self.gramm_cat_order.iter(),
+        }
+    }
+
+    /// Returns an iterator to the grammatical categories in the given order of their names.
+    ///
+    /// A replaced category keeps it's place in the insertion order.
+    pub fn iter_gramm_cats_ordered(&self, order: &SortOrder) -> vec::IntoIter<GrammCategory> {
+        let mut cats: Vec<GrammCategory> = self.iter_gramm_cats().collect();
+        cats.sort_by_cached_key(|c| order.sort_key(&c.get_name()));
+        cats.into_iter()
     }
 
     // GRAMMEMES
@@ -129,34 +1101,369 @@ impl Encyclopedia {
         }
     }
 
+    // AFFIX CLASSES
+
+    /// Adds an affix class, replacing any previous class with the same name.
+    ///
+    /// Fails if the grammemes of the rules aren't valid values of stored grammatical
+    /// categories.
+    pub fn add_affix_class(&mut self, class: AffixClass) -> LingResult<()> {
+        try!(self.check_affix_class(&class));
+        self.affix_classes.insert(class.get_name().to_owned(), class);
+        Ok(())
+    }
+
+    /// Checks that the grammemes of the rules are valid values of stored grammatical categories.
+    pub(crate) fn check_affix_class(&self, class: &AffixClass) -> LingResult<()> {
+        for rule in class.get_rules() {
+            for &(ref category, ref value) in rule.get_grammemes() {
+                try!(self.create_grammeme(category, Some(value)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the affix class with the given name.
+    pub fn get_affix_class<T: AsRef<str>>(&self, name: T) -> Option<&AffixClass> {
+        self.affix_classes.get(name.as_ref())
+    }
+
+    /// Removes the affix class with the given name.
+    pub fn remove_affix_class<T: AsRef<str>>(&mut self, name: T) -> Option<AffixClass> {
+        self.affix_classes.remove(name.as_ref())
+    }
+
+    /// Returns the amount of stored affix classes.
+    pub fn affix_class_amount(&self) -> usize {
+        self.affix_classes.len()
+    }
+
+    /// Returns an iterator to the affix classes.
+    pub fn iter_affix_classes(&self) -> hash_map::Values<String, AffixClass> {
+        self.affix_classes.values()
+    }
+
+    // REQUIRED FORMS
+
+    /// Requires the words with a `LingTag::Type` tag of the given type to have a form with the
+    /// grammemes, see `Encyclopedia::coverage`.
+    ///
+    /// Fails if the grammemes aren't valid values of stored grammatical categories.
+    pub fn add_required_form<T: Into<String>>(&mut self,
+                                              word_type: T,
+                                              grammemes: &[Grammeme])
+                                              -> LingResult<()> {
+        let mut form = Vec::new();
+        for g in grammemes {
+            let (category, value) = (g.get_category_name(), g.get_value());
+            try!(self.create_grammeme(&category, Some(&value)));
+            form.push(Grammeme::new(Cow::Owned(category.into_owned()),
+                                    Cow::Owned(value.into_owned())));
+        }
+        let forms = self.required_forms.entry(word_type.into()).or_insert_with(Vec::new);
+        if !forms.contains(&form) {
+            forms.push(form);
+        }
+        Ok(())
+    }
+
+    /// Returns the grammemes of the forms required for the words of the given type, in the
+    /// order they were added.
+    pub fn get_required_forms<T: AsRef<str>>(&self, word_type: T) -> &[Vec<Grammeme<'static>>] {
+        self.required_forms.get(word_type.as_ref()).map_or(&[], |f| f as &[_])
+    }
+
+    /// Removes the required forms of the given type.
+    pub fn remove_required_forms<T: AsRef<str>>(&mut self, word_type: T) {
+        self.required_forms.remove(word_type.as_ref());
+    }
+
+    // PSEUDO-LOCALIZATION
+
+    /// Enables or disables the pseudo-localization of the rendered texts, see
+    /// `Encyclopedia::render_name` and `pseudo_localize`.
+    pub fn set_pseudo_locale(&mut self, enabled: bool) {
+        self.pseudo_locale = enabled;
+    }
+
+    /// Returns true if the rendered texts are pseudo-localized.
+    pub fn is_pseudo_locale_enabled(&self) -> bool {
+        self.pseudo_locale
+    }
+
+    /// Sets the locale of the texts the translations are made from, like `en`.
+    ///
+    /// The tag groups with a text in the source locale need a translation, see
+    /// `Encyclopedia::coverage`.
+    pub fn set_source_locale<T: Into<String>>(&mut self, locale: Option<T>) {
+        self.source_locale = locale.map(Into::into);
+    }
+
+    /// Returns the locale of the texts the translations are made from.
+    pub fn get_source_locale(&self) -> Option<&str> {
+        self.source_locale.as_ref().map(|l| l as &str)
+    }
+
+    // CHANGE LOG
+
+    /// Starts recording the changes made to this encyclopedia into the change log.
+    ///
+    /// The recorded changes can be read with `drain_changes`.
+    /// The log is disabled by default, since it grows until it's drained.
+    pub fn enable_change_log(&mut self) {
+        if self.change_log.is_none() {
+            self.change_log = Some(Vec::new());
+        }
+    }
+
+    /// Stops recording the changes and clears the change log.
+    pub fn disable_change_log(&mut self) {
+        self.change_log = None;
+    }
+
+    /// Returns true if the changes are being recorded.
+    pub fn is_change_log_enabled(&self) -> bool {
+        self.change_log.is_some()
+    }
+
+    /// Removes the recorded changes from the change log and returns an iterator over them in the
+    /// order they were made.
+    pub fn drain_changes(&mut self) -> vec::IntoIter<EncChange<U>> {
+        match self.change_log {
+            Some(ref mut log) => log.split_off(0).into_iter(),
+            None => Vec::new().into_iter(),
+        }
+    }
+
+    /// Records the changes if the change log is enabled.
+    fn log_changes(&mut self, changes: Vec<EncChange<U>>) {
+        if let Some(ref mut log) = self.change_log {
+            log.extend(changes);
+        }
+    }
+
+    // SAVED STATES
+
+    /// Saves the current state of the word with exactly the given name, so it can be restored
+    /// with `restore` without recording any changes.
+    pub(crate) fn save_word(&self, name: &str) -> SavedEntry<U> {
+        let id = self.word_table.get(name);
+        let entry = id.and_then(|id| self.entry(id)).cloned();
+        let provenance = match (&self.provenance, id) {
+            (&Some(ref map), Some(id)) => map.get(&id).cloned(),
+            _ => None,
+        };
+        let aliases = self.aliases
+            .iter()
+            .filter(|&(_, &alias)| Some(alias) == id)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        SavedEntry(Saved::Word(name.to_owned(), entry, provenance, aliases))
+    }
+
+    /// Saves the current state of the tag group, see `save_word`.
+    pub(crate) fn save_tag_group(&self, name: &str) -> SavedEntry<U> {
+        SavedEntry(Saved::TagGroup(name.to_owned(), self.tag_group(name).cloned()))
+    }
+
+    /// Saves the current state of the grammatical category, see `save_word`.
+    pub(crate) fn save_gramm_cat(&self, name: &str) -> SavedEntry<U> {
+        let position = self.gramm_cat_order.iter().position(|n| n == name);
+        SavedEntry(Saved::GrammCat(name.to_owned(), self.gramm_cats.get(name).cloned(), position))
+    }
+
+    /// Restores a saved state exactly as it was, with the revisions and the provenance of the
+    /// words. The change isn't recorded into the change log.
+    pub(crate) fn restore(&mut self, saved: SavedEntry<U>) {
+        match saved.0 {
+            Saved::Word(name, entry, provenance, aliases) => {
+                let id = self.word_table.intern(&name);
+                self.aliases.retain(|alias, &mut alias_id| {
+                    alias_id != id || aliases.contains(alias)
+                });
+                for alias in aliases {
+                    self.aliases.insert(alias, id);
+                }
+                if let Some(ref mut map) = self.provenance {
+                    match provenance {
+                        Some(provenance) => map.insert(id, provenance),
+                        None => map.remove(&id),
+                    };
+                }
+                self.set_entry(id, entry);
+            }
+            Saved::TagGroup(name, tags) => {
+                let sym = self.word_table.symbol(&name);
+                match tags {
+                    Some(group) => self.tag_groups.insert(sym, group),
+                    None => self.tag_groups.remove(&sym),
+                };
+            }
+            Saved::GrammCat(name, values, position) => {
+                self.gramm_cat_order.retain(|n| *n != name);
+                if let Some(position) = position {
+                    let position = cmp::min(position, self.gramm_cat_order.len());
+                    self.gramm_cat_order.insert(position, name.clone());
+                }
+                match values {
+                    Some(values) => self.gramm_cats.insert(name, values),
+                    None => self.gramm_cats.remove(&name),
+                };
+            }
+        }
+    }
+
+    /// Restores a saved state like `restore`, but records the changes into the change log like
+    /// the edits that would lead to the state.
+    pub(crate) fn restore_logged(&mut self, saved: SavedEntry<U>) {
+        if self.change_log.is_some() {
+            let changes = self.restore_changes(&saved);
+            self.log_changes(changes);
+        }
+        self.restore(saved);
+    }
+
+    /// Returns the changes restoring the saved state would make.
+    fn restore_changes(&self, saved: &SavedEntry<U>) -> Vec<EncChange<U>> {
+        match saved.0 {
+            Saved::Word(ref name, ref entry, _, _) => {
+                let old = self.word_table.get(name).and_then(|id| self.entry(id));
+                match (old, entry.as_ref()) {
+                    (None, None) => Vec::new(),
+                    (None, Some(_)) => vec![EncChange::WordAdded(name.clone())],
+                    (Some(_), None) => vec![EncChange::WordRemoved(name.clone())],
+                    (Some(old), Some(new)) => {
+                        let mut changes = vec![EncChange::WordReplaced(name.clone())];
+                        changes.extend(old.loaded
+                            .difference(&new.loaded)
+                            .map(|t| EncChange::TagRemoved(name.clone(), t.clone())));
+                        changes.extend(new.loaded
+                            .difference(&old.loaded)
+                            .map(|t| EncChange::TagAdded(name.clone(), t.clone())));
+                        changes
+                    }
+                }
+            }
+            Saved::TagGroup(ref name, ref group) => {
+                let old = self.tag_group(name).is_some();
+                replace_changes(name, old, group.is_some(), EncChange::TagGroupAdded,
+                                EncChange::TagGroupReplaced, EncChange::TagGroupRemoved)
+            }
+            Saved::GrammCat(ref name, ref values, _) => {
+                let old = self.gramm_cats.contains_key(name);
+                replace_changes(name, old, values.is_some(), EncChange::GrammCatAdded,
+                                EncChange::GrammCatReplaced, EncChange::GrammCatRemoved)
+            }
+        }
+    }
+
+    /// Returns the length of the change log, or `None` if it isn't enabled.
+    pub(crate) fn change_log_len(&self) -> Option<usize> {
+        self.change_log.as_ref().map(Vec::len)
+    }
+
+    /// Forgets the changes recorded after the change log had the given length.
+    pub(crate) fn truncate_change_log(&mut self, len: usize) {
+        if let Some(ref mut log) = self.change_log {
+            log.truncate(len);
+        }
+    }
+
     // OTHER
 
     /// Tells if the encyclopedia has no words or grammatical categories.
     ///
     /// `enc.is_empty()` is equivalent to `enc.word_amount() == 0 && enc.gramm_cat_amount() == 0`.
     pub fn is_empty(&self) -> bool {
-        self.word_map.is_empty() && self.gramm_cats.is_empty()
+        self.word_count == 0 && self.gramm_cats.is_empty()
     }
 }
 
+/// The saved state of a word, a tag group or a grammatical category, see
+/// `Encyclopedia::save_word`.
+#[derive(Clone)]
+pub(crate) struct SavedEntry<U>(Saved<U>);
+
+#[derive(Clone)]
+enum Saved<U> {
+    /// The name of a word, it's entry, the provenance of it's tags and it's aliases.
+    Word(String,
+         Option<WordEntry<U>>,
+         Option<HashMap<StoredTag<U>, Provenance>>,
+         Vec<String>),
+    TagGroup(String, Option<TagGroupEntry<U>>),
+    /// The name of a category, it's values and it's place in the insertion order.
+    GrammCat(String, Option<(Option<String>, HashSet<String>)>, Option<usize>),
+}
+
+/// Returns the change of replacing an entry that exists if `old` is true with one that exists
+/// if `new` is true.
+fn replace_changes<U>(name: &str,
+                      old: bool,
+                      new: bool,
+                      added: fn(String) -> EncChange<U>,
+                      replaced: fn(String) -> EncChange<U>,
+                      removed: fn(String) -> EncChange<U>)
+                      -> Vec<EncChange<U>> {
+    match (old, new) {
+        (false, false) => Vec::new(),
+        (false, true) => vec![added(name.to_owned())],
+        (true, true) => vec![replaced(name.to_owned())],
+        (true, false) => vec![removed(name.to_owned())],
+    }
+}
+
+/// Returns the id of the word stored at the index of `Encyclopedia::words`.
+fn stored_id(index: usize) -> WordId {
+    // the ids of the stored words are valid
+    WordId::from_index(index).unwrap()
+}
+
+/// Tells what was changed by renaming a word or a tag group.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RenameReport {
+    /// The names of the words whose references were rewritten, in sorted order.
+    pub words: Vec<String>,
+    /// The names of the tag groups whose references were rewritten, in sorted order.
+    pub tag_groups: Vec<String>,
+    /// True if the old name was left as an alias.
+    pub alias_left: bool,
+}
+
 /// An iterator that goes through all of the words in an encyclopedia.
-pub struct WordIter<'a> {
-    iter: hash_map::Iter<'a, String, HashSet<LingTag>>,
+pub struct WordIter<'a, U: UserTag = NoUserTag> {
+    enc: &'a GenericEncyclopedia<U>,
+    iter: slice::Iter<'a, (u64, WordId)>,
+}
+
+impl<'a, U: UserTag> Iterator for WordIter<'a, U> {
+    type Item = GenericWord<'a, U>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|&(_, id)| self.enc.make_word(id))
+    }
+}
+
+/// An iterator that goes through all of the tag groups in an encyclopedia.
+pub struct TagGroupIter<'a, U: UserTag = NoUserTag> {
+    table: &'a WordTable,
+    iter: hash_map::Iter<'a, Symbol, TagGroupEntry<U>>,
 }
 
-impl<'a> Iterator for WordIter<'a> {
-    type Item = Word<'a>;
+impl<'a, U: UserTag> Iterator for TagGroupIter<'a, U> {
+    type Item = (&'a str, &'a HashSet<LingTag<U>>);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
-            Some((name, tags)) => Some(Word::new_from_collection(name.deref(), tags)),
+            Some((&name, group)) => Some((self.table.resolve(name), &group.loaded)),
             None => None,
         }
     }
 }
 
 pub struct GrammCatIter<'a> {
-    iter: hash_map::Iter<'a, String, (Option<String>, HashSet<String>)>,
+    gramm_cats: &'a HashMap<String, (Option<String>, HashSet<String>)>,
+    iter: slice::Iter<'a, String>,
 }
 
 impl<'a> Iterator for GrammCatIter<'a> {
@@ -164,7 +1471,8 @@ impl<'a> Iterator for GrammCatIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
-            Some((name, tuple)) => {
+            Some(name) => {
+                let tuple = &self.gramm_cats[name];
                 Some(GrammCategory::new(name as &str,
                                         tuple.0.clone(),
                                         tuple.1.iter().map(&AsRef::as_ref)))
@@ -0,0 +1,78 @@
+//! Contains the writer for the Graphviz DOT format.
+//!
+//! The graph shows the taxonomy and the containment of the words. Every stored word is a node,
+//! `LingTag::Parent` is a solid edge from the word to it's parent and `LingTag::HasWords` is a
+//! dashed edge from the word to every word it contains. The parents are drawn above their
+//! children.
+//!
+//! ```text
+//! digraph encyclopedia {
+//!     rankdir=BT;
+//!     "goblin";
+//!     "monster";
+//!     "goblin" -> "monster";
+//!     "goblin" -> "arm" [style=dashed, label="has"];
+//! }
+//! ```
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::ling::{LingTag, UserTag};
+
+/// Writes the taxonomy and containment graph of the words into DOT text.
+///
+/// The nodes and edges are sorted by name, so the same words always produce the same text.
+/// The contained words that aren't stored aren't written as nodes, but Graphviz creates them
+/// from the edges.
+pub fn write_dot<U: UserTag>(enc: &GenericEncyclopedia<U>) -> String {
+    let mut nodes = BTreeSet::new();
+    let mut parents = BTreeSet::new();
+    let mut contained = BTreeSet::new();
+    for word in enc.iter_words() {
+        let name = word.get_name().into_owned();
+        for tag in word.get_tags() {
+            match **tag {
+                LingTag::Parent(ref parent) => {
+                    if let Some(parent) = enc.resolve_name(parent) {
+                        parents.insert((name.clone(), parent.to_owned()));
+                    }
+                }
+                LingTag::HasWords(ref words) => {
+                    for w in words {
+                        contained.insert((name.clone(), w.clone()));
+                    }
+                }
+                _ => (),
+            }
+        }
+        nodes.insert(name);
+    }
+
+    let mut out = String::from("digraph encyclopedia {\n    rankdir=BT;\n");
+    for node in &nodes {
+        writeln!(out, "    {};", quote(node)).unwrap();
+    }
+    for &(ref child, ref parent) in &parents {
+        writeln!(out, "    {} -> {};", quote(child), quote(parent)).unwrap();
+    }
+    for &(ref word, ref part) in &contained {
+        writeln!(out, "    {} -> {} [style=dashed, label=\"has\"];", quote(word), quote(part))
+            .unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Returns the string as a quoted DOT identifier.
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
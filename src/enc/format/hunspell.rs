@@ -0,0 +1,382 @@
+//! Contains the importer for the Hunspell dictionary and affix files.
+//!
+//! The `PFX` and `SFX` classes of the affix file are imported as `AffixClass`es named after their
+//! flags, and the words of the dictionary file are imported as words that list the classes of
+//! their flags in the `LingTag::CustomSet` tag with the key `AFFIX_KEY`. This is enough for
+//! `Encyclopedia::inflections` and `Encyclopedia::lemmatize` to work with the imported words.
+//!
+//! Hunspell has no grammatical categories, so the grammemes of the imported rules are given
+//! with `HunspellImport::map_flag` and `HunspellImport::map_morph`.
+//! The features the importer doesn't support, like compounding, continuation classes or the
+//! suggestion options, are skipped and listed in an `ImportReport`.
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::{GenericWord, Word};
+use enc::ling::{LingTag, UserTag};
+use enc::ling::affix::{AFFIX_KEY, AffixClass, AffixKind, AffixRule};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The file a skipped feature was found in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HunspellFile {
+    Affix,
+    Dictionary,
+}
+
+/// A feature of the imported files that was skipped.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Unsupported {
+    /// A description of the feature, like ``directive `COMPOUNDRULE` ``.
+    pub feature: String,
+    pub file: HunspellFile,
+    /// The first line the feature was found at.
+    pub line: usize,
+    /// How many times the feature was found.
+    pub count: usize,
+}
+
+/// The features skipped when importing Hunspell files, in the order they were found.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct ImportReport {
+    pub unsupported: Vec<Unsupported>,
+}
+
+impl ImportReport {
+    /// Returns true if everything in the files was imported.
+    pub fn is_empty(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+
+    fn add(&mut self, file: HunspellFile, line: usize, feature: String) {
+        match self.unsupported.iter_mut().find(|u| u.file == file && u.feature == feature) {
+            Some(u) => u.count += 1,
+            None => {
+                self.unsupported.push(Unsupported {
+                    feature: feature,
+                    file: file,
+                    line: line,
+                    count: 1,
+                })
+            }
+        }
+    }
+}
+
+/// The words and affix classes imported from Hunspell files.
+#[derive(Clone, Debug)]
+pub struct HunspellDictionary {
+    pub words: Vec<Word<'static>>,
+    pub classes: Vec<AffixClass>,
+    pub report: ImportReport,
+}
+
+impl HunspellDictionary {
+    /// Adds the affix classes and the words into the encyclopedia.
+    ///
+    /// The words are completed and checked with `Encyclopedia::apply_schema`. Fails if the
+    /// grammemes of the affix rules aren't valid, see `Encyclopedia::add_affix_class`, or with
+    /// `LingError::InvalidWord` if a word isn't valid. Everything is checked before anything is
+    /// added, so on failure the encyclopedia is left unchanged.
+    pub fn add_to<U: UserTag>(self, enc: &mut GenericEncyclopedia<U>) -> LingResult<()> {
+        let mut words: Vec<GenericWord<U>> =
+            self.words.into_iter().map(Word::with_user_tag).collect();
+        for word in &mut words {
+            if let Err(e) = enc.apply_schema(word) {
+                return Err(LingError::InvalidWord(word.get_name().into_owned(), Box::new(e)));
+            }
+        }
+        for class in &self.classes {
+            try!(enc.check_affix_class(class));
+        }
+        for class in self.classes {
+            try!(enc.add_affix_class(class));
+        }
+        for word in words {
+            enc.add_word(word);
+        }
+        Ok(())
+    }
+}
+
+/// The way the flags are written in the files.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum FlagType {
+    /// Every character is a flag.
+    Char,
+    /// Every two characters are a flag.
+    Long,
+    /// The flags are numbers separated by commas.
+    Num,
+}
+
+impl FlagType {
+    fn split(&self, s: &str) -> Vec<String> {
+        match *self {
+            FlagType::Char => s.chars().map(|c| c.to_string()).collect(),
+            FlagType::Long => {
+                let chars: Vec<char> = s.chars().collect();
+                chars.chunks(2).map(|c| c.iter().cloned().collect()).collect()
+            }
+            FlagType::Num => {
+                s.split(',').filter(|f| !f.is_empty()).map(ToOwned::to_owned).collect()
+            }
+        }
+    }
+}
+
+/// An importer for the Hunspell files, with the grammemes given to the imported rules.
+#[derive(Clone, Debug, Default)]
+pub struct HunspellImport {
+    flag_grammemes: HashMap<String, Vec<(String, String)>>,
+    morph_grammemes: HashMap<String, Vec<(String, String)>>,
+}
+
+impl HunspellImport {
+    pub fn new() -> Self {
+        HunspellImport::default()
+    }
+
+    /// Gives the grammeme to every rule of the affix class with the given flag.
+    pub fn map_flag<T, V, W>(mut self, flag: T, category: V, value: W) -> Self
+        where T: Into<String>,
+              V: Into<String>,
+              W: Into<String>
+    {
+        self.flag_grammemes
+            .entry(flag.into())
+            .or_insert_with(Vec::new)
+            .push((category.into(), value.into()));
+        self
+    }
+
+    /// Gives the grammeme to every affix rule with the morphological field, like `is:plural`.
+    ///
+    /// The fields that aren't mapped are listed in the report.
+    pub fn map_morph<T, V, W>(mut self, field: T, category: V, value: W) -> Self
+        where T: Into<String>,
+              V: Into<String>,
+              W: Into<String>
+    {
+        self.morph_grammemes
+            .entry(field.into())
+            .or_insert_with(Vec::new)
+            .push((category.into(), value.into()));
+        self
+    }
+
+    /// Reads and imports the affix file and the dictionary file.
+    ///
+    /// The files have to be encoded in UTF-8.
+    pub fn read_files<P, Q>(&self, aff: P, dic: Q) -> LingResult<HunspellDictionary>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        let mut aff_src = String::new();
+        try!(try!(File::open(aff)).read_to_string(&mut aff_src));
+        let mut dic_src = String::new();
+        try!(try!(File::open(dic)).read_to_string(&mut dic_src));
+        self.parse(&aff_src, &dic_src)
+    }
+
+    /// Imports the contents of an affix file and a dictionary file.
+    pub fn parse(&self, aff: &str, dic: &str) -> LingResult<HunspellDictionary> {
+        let mut report = ImportReport::default();
+        let (flag_type, classes) = try!(self.parse_affixes(aff, &mut report));
+        let words = parse_dictionary(dic, flag_type, &classes, &mut report);
+        Ok(HunspellDictionary {
+            words: words,
+            classes: classes,
+            report: report,
+        })
+    }
+
+    fn parse_affixes(&self,
+                     src: &str,
+                     report: &mut ImportReport)
+                     -> LingResult<(FlagType, Vec<AffixClass>)> {
+        let mut flag_type = FlagType::Char;
+        let mut classes: Vec<AffixClass> = Vec::new();
+        // the amounts of the rules left to read in the affix classes
+        let mut pending: HashMap<String, usize> = HashMap::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let line_num = i + 1;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let directive = match fields.first() {
+                Some(d) if !d.starts_with('#') => *d,
+                _ => continue,
+            };
+            let err = |msg: &str| LingError::ParseError(line_num, msg.to_owned());
+
+            match directive {
+                "SET" => {
+                    let encoding = try!(fields.get(1).ok_or(err("missing encoding")));
+                    if !encoding.eq_ignore_ascii_case("UTF-8") {
+                        report.add(HunspellFile::Affix,
+                                   line_num,
+                                   format!("encoding `{}`", encoding));
+                    }
+                }
+                "FLAG" => {
+                    flag_type = match fields.get(1) {
+                        Some(&"long") => FlagType::Long,
+                        Some(&"num") => FlagType::Num,
+                        Some(&"UTF-8") => FlagType::Char,
+                        Some(other) => {
+                            report.add(HunspellFile::Affix,
+                                       line_num,
+                                       format!("flag type `{}`", other));
+                            FlagType::Char
+                        }
+                        None => return Err(err("missing flag type")),
+                    }
+                }
+                "PFX" | "SFX" => {
+                    if fields.len() < 4 {
+                        return Err(err("too few fields in an affix line"));
+                    }
+                    let flag = fields[1].to_owned();
+                    let remaining = pending.get(&flag).cloned().unwrap_or(0);
+                    if remaining == 0 {
+                        // the header of a new class
+                        let count = try!(fields[3].parse().map_err(|_| err("invalid rule count")));
+                        let kind = if directive == "PFX" {
+                            AffixKind::Prefix
+                        } else {
+                            AffixKind::Suffix
+                        };
+                        classes.push(AffixClass::new(flag.clone(), kind)
+                            .cross_product(fields[2] == "Y"));
+                        pending.insert(flag, count);
+                        continue;
+                    }
+                    pending.insert(flag.clone(), remaining - 1);
+                    if let Some(rule) = self.parse_rule(&fields, line_num, flag_type, report) {
+                        let class = classes.iter_mut().rev().find(|c| c.get_name() == flag);
+                        class.unwrap().add_rule(rule);
+                    }
+                }
+                other => {
+                    report.add(HunspellFile::Affix, line_num, format!("directive `{}`", other));
+                }
+            }
+        }
+        Ok((flag_type, classes))
+    }
+
+    /// Parses a rule line, or returns `None` if the rule can't be imported.
+    fn parse_rule(&self,
+                  fields: &[&str],
+                  line_num: usize,
+                  flag_type: FlagType,
+                  report: &mut ImportReport)
+                  -> Option<AffixRule> {
+        let zero = |s: &str| if s == "0" { String::new() } else { s.to_owned() };
+        let strip = zero(fields[2]);
+        let (add, continuation) = match fields[3].find('/') {
+            Some(i) => (zero(&fields[3][..i]), flag_type.split(&fields[3][i + 1..])),
+            None => (zero(fields[3]), Vec::new()),
+        };
+        if !continuation.is_empty() {
+            report.add(HunspellFile::Affix, line_num, "continuation classes".to_owned());
+        }
+        let condition = fields.get(4).cloned().unwrap_or(".");
+        let mut rule = match AffixRule::new(strip, add, condition) {
+            Ok(rule) => rule,
+            Err(_) => {
+                report.add(HunspellFile::Affix, line_num, format!("condition `{}`", condition));
+                return None;
+            }
+        };
+
+        let flag_grammemes = self.flag_grammemes.get(fields[1]).into_iter().flat_map(|g| g);
+        for &(ref category, ref value) in flag_grammemes {
+            rule = rule.with_grammeme(category.clone(), value.clone());
+        }
+        for field in fields.iter().skip(5) {
+            match self.morph_grammemes.get(*field) {
+                Some(grammemes) => {
+                    for &(ref category, ref value) in grammemes {
+                        rule = rule.with_grammeme(category.clone(), value.clone());
+                    }
+                }
+                None => {
+                    report.add(HunspellFile::Affix,
+                               line_num,
+                               format!("morphological field `{}`", field));
+                }
+            }
+        }
+        Some(rule)
+    }
+}
+
+/// Parses the words of a dictionary file. The words listed many times are merged.
+fn parse_dictionary(src: &str,
+                    flag_type: FlagType,
+                    classes: &[AffixClass],
+                    report: &mut ImportReport)
+                    -> Vec<Word<'static>> {
+    let mut names: Vec<String> = Vec::new();
+    let mut flags: HashMap<String, BTreeSet<String>> = HashMap::new();
+
+    for (i, line) in src.lines().enumerate() {
+        let line_num = i + 1;
+        let mut fields = line.split_whitespace();
+        let entry = match fields.next() {
+            // the first line has the approximate amount of words
+            Some(e) if i == 0 && e.parse::<usize>().is_ok() => continue,
+            Some(e) => e,
+            None => continue,
+        };
+        for field in fields {
+            let name = field.split(':').next().unwrap();
+            report.add(HunspellFile::Dictionary,
+                       line_num,
+                       format!("morphological field `{}`", name));
+        }
+
+        // a slash in a word is escaped with a backslash
+        let split = entry.char_indices()
+            .find(|&(i, c)| c == '/' && !entry[..i].ends_with('\\'))
+            .map(|(i, _)| i);
+        let (word, word_flags) = match split {
+            Some(i) => (&entry[..i], flag_type.split(&entry[i + 1..])),
+            None => (entry, Vec::new()),
+        };
+        let word = word.replace("\\/", "/");
+
+        if !flags.contains_key(&word) {
+            names.push(word.clone());
+        }
+        let entry = flags.entry(word).or_insert_with(BTreeSet::new);
+        for flag in word_flags {
+            if classes.iter().any(|c| c.get_name() == flag) {
+                entry.insert(flag);
+            } else {
+                report.add(HunspellFile::Dictionary,
+                           line_num,
+                           format!("flag `{}` without an affix class", flag));
+            }
+        }
+    }
+
+    names.into_iter()
+        .map(|name| {
+            let word_flags = flags.remove(&name).unwrap();
+            if word_flags.is_empty() {
+                Word::new(name)
+            } else {
+                Word::new_from_collection(name,
+                                          vec![LingTag::CustomSet(AFFIX_KEY.to_owned(),
+                                                                  word_flags)])
+            }
+        })
+        .collect()
+}
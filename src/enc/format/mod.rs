@@ -0,0 +1,10 @@
+//! Contains readers and writers for the file formats encyclopedias can be stored in.
+pub mod source;
+pub mod dot;
+pub mod wordnet;
+pub mod hunspell;
+pub mod table;
+pub mod po;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,380 @@
+//! Contains the export and import of the gettext PO and POT translation files.
+//!
+//! Every word name and every inflected form of the words is a translatable message, see
+//! `Encyclopedia::inflections`. The message id is the name or the form, and the message context
+//! has the name of the word followed by the grammemes of the form, like `goblin` for the name of
+//! the word and `goblin|number=plural` for it's plural form. The `|` and `\` characters of the
+//! names are escaped with a `\` in the contexts, so a name can't be mistaken for grammemes.
+//!
+//! The contexts are keyed on the names, because the `WordId`s depend on the order the names
+//! were added in. A renamed word can be found with it's old name if the name was kept as an
+//! alias, see `Encyclopedia::rename_word`, and the entries whose message ids aren't the current
+//! names or forms aren't imported.
+//!
+//! The translations are stored in the `LingTag::CustomStr` tags of the words, with the keys
+//! made by `locale_key`.
+//!
+//! ```text
+//! msgctxt "goblin|number=plural"
+//! msgid "goblins"
+//! msgstr "peikot"
+//! ```
+use std::fmt::Write;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::grammeme::Grammeme;
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// Returns the key of the tag with the translation of the form with the grammemes into the
+/// locale, like `fi` for the translation of the word name and `fi|number=plural` for the
+/// translation of a form.
+pub fn locale_key(locale: &str, grammemes: &[Grammeme]) -> String {
+    form_key(locale, &grammeme_list(grammemes))
+}
+
+/// Returns the key of the translation of the form with the grammemes listed like in the
+/// message contexts.
+fn form_key(locale: &str, grammemes: &str) -> String {
+    if grammemes.is_empty() {
+        locale.to_owned()
+    } else {
+        format!("{}|{}", locale, grammemes)
+    }
+}
+
+/// Tells what was done when importing a PO file.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct PoReport {
+    /// The contexts of the imported translations.
+    pub imported: Vec<String>,
+    /// The contexts of the fuzzy translations, which were not imported.
+    pub fuzzy: Vec<String>,
+    /// The contexts of the obsolete entries, which were not imported.
+    pub obsolete: Vec<String>,
+    /// The contexts of the entries without a translation.
+    pub untranslated: Vec<String>,
+    /// The contexts of the entries that refer to words or forms that don't exist, with the
+    /// lines of the entries.
+    pub unknown: Vec<(usize, String)>,
+    /// The contexts of the entries whose message ids aren't the current name or form of the
+    /// word, with the lines of the entries. The texts have changed since the file was written,
+    /// so the translations weren't imported.
+    pub changed: Vec<(usize, String)>,
+    /// The contexts of the translations rejected by the schema of the encyclopedia, with the
+    /// lines of the entries and the errors.
+    pub rejected: Vec<(usize, String, String)>,
+}
+
+/// Writes the translatable messages of the words into a POT file.
+pub fn write_pot<U: UserTag>(enc: &GenericEncyclopedia<U>) -> String {
+    write_messages(enc, None)
+}
+
+/// Writes the translatable messages of the words into a PO file with the translations stored
+/// for the locale.
+pub fn write_po<U: UserTag>(enc: &GenericEncyclopedia<U>, locale: &str) -> String {
+    write_messages(enc, Some(locale))
+}
+
+/// Writes the messages in the order the words were added in, with the name of each word
+/// followed by it's forms.
+fn write_messages<U: UserTag>(enc: &GenericEncyclopedia<U>, locale: Option<&str>) -> String {
+    let mut out = String::from("msgid \"\"\nmsgstr \"\"\n");
+    out.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    if let Some(locale) = locale {
+        writeln!(out, "\"Language: {}\\n\"", escape(locale)).unwrap();
+    }
+
+    for word in enc.iter_words() {
+        let name = word.get_name().into_owned();
+        let escaped = escape_name(&name);
+        let mut messages = vec![(escaped.clone(), name.clone(), String::new())];
+        for (grammemes, form) in forms(enc, &name) {
+            messages.push((format!("{}|{}", escaped, grammemes), form, grammemes));
+        }
+
+        for (context, msgid, grammemes) in messages {
+            let msgstr = locale.and_then(|locale| {
+                word.get_custom_str(&form_key(locale, &grammemes)).map(ToOwned::to_owned)
+            });
+            write!(out,
+                   "\nmsgctxt \"{}\"\nmsgid \"{}\"\nmsgstr \"{}\"\n",
+                   escape(&context),
+                   escape(&msgid),
+                   escape(msgstr.as_ref().map_or("", |s| s as &str)))
+                .unwrap();
+        }
+    }
+    out
+}
+
+/// Imports the translations of a PO file into the encyclopedia.
+///
+/// The locale is read from the `Language` header of the file if it isn't given. The
+/// translations replace the earlier translations of the same forms. The fuzzy, obsolete and
+/// untranslated entries, the entries of unknown words or forms, the entries whose names or
+/// forms have changed and the translations rejected by the schema are skipped and listed in the
+/// report.
+///
+/// Fails if the file can't be parsed or if the locale isn't known.
+pub fn import_po<U: UserTag>(enc: &mut GenericEncyclopedia<U>,
+                             src: &str,
+                             locale: Option<&str>)
+                             -> LingResult<PoReport> {
+    let entries = try!(parse_entries(src));
+    let header_locale = entries.iter()
+        .find(|e| e.msgctxt.is_none() && e.msgid.is_empty())
+        .and_then(|e| {
+            e.msgstr
+                .lines()
+                .find(|l| l.starts_with("Language:"))
+                .map(|l| l["Language:".len()..].trim().to_owned())
+        });
+    let locale = match locale.map(ToOwned::to_owned).or(header_locale) {
+        Some(ref locale) if !locale.is_empty() => locale.clone(),
+        _ => return Err(LingError::ParseError(1, "unknown locale".to_owned())),
+    };
+
+    let mut report = PoReport::default();
+    for entry in entries {
+        let context = match entry.msgctxt {
+            Some(context) => context,
+            None => continue,
+        };
+        if entry.obsolete {
+            report.obsolete.push(context);
+            continue;
+        }
+        if entry.fuzzy {
+            report.fuzzy.push(context);
+            continue;
+        }
+        if entry.msgstr.is_empty() {
+            report.untranslated.push(context);
+            continue;
+        }
+
+        let word = split_context(&context).and_then(|(name, grammemes)| {
+            enc.get_word(&name).map(|w| (w.get_name().into_owned(), grammemes))
+        });
+        let (name, grammemes) = match word {
+            Some(word) => word,
+            None => {
+                report.unknown.push((entry.line, context));
+                continue;
+            }
+        };
+        let text = if grammemes.is_empty() {
+            Some(name.clone())
+        } else {
+            forms(enc, &name).into_iter().find(|f| f.0 == grammemes).map(|f| f.1)
+        };
+        match text {
+            Some(ref text) if *text == entry.msgid => (),
+            Some(_) => {
+                report.changed.push((entry.line, context));
+                continue;
+            }
+            None => {
+                report.unknown.push((entry.line, context));
+                continue;
+            }
+        }
+
+        let key = form_key(&locale, grammemes);
+        match enc.add_word_tag(&name, LingTag::CustomStr(key, Some(entry.msgstr))) {
+            Ok(_) => report.imported.push(context),
+            Err(e) => report.rejected.push((entry.line, context, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Returns the grammemes of the forms of the word listed like in the message contexts, with the
+/// first form created with each of them.
+fn forms<U: UserTag>(enc: &GenericEncyclopedia<U>, name: &str) -> Vec<(String, String)> {
+    let mut forms: Vec<(String, String)> = Vec::new();
+    for form in enc.inflections(name).unwrap_or_default() {
+        let grammemes = grammeme_list(&form.grammemes);
+        // many rules can create the same form with the same grammemes
+        if !forms.iter().any(|f| f.0 == grammemes) {
+            forms.push((grammemes, form.form));
+        }
+    }
+    forms
+}
+
+/// Escapes the `|` and `\` characters of a word name for a message context.
+fn escape_name(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Splits a message context into the unescaped word name and the grammemes after the first
+/// unescaped `|`.
+///
+/// Returns `None` if the name has an invalid escape.
+fn split_context(context: &str) -> Option<(String, &str)> {
+    let mut name = String::new();
+    let mut chars = context.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Some((_, c)) if c == '\\' || c == '|' => name.push(c),
+                    _ => return None,
+                }
+            }
+            '|' => return Some((name, &context[i + 1..])),
+            c => name.push(c),
+        }
+    }
+    Some((name, ""))
+}
+
+/// Returns the grammemes in the form `category=value,category=value`.
+fn grammeme_list(grammemes: &[Grammeme]) -> String {
+    let list: Vec<String> = grammemes.iter()
+        .map(|g| format!("{}={}", g.get_category_name(), g.get_value()))
+        .collect();
+    list.join(",")
+}
+
+/// An entry of a PO file.
+#[derive(Default)]
+struct Entry {
+    /// The line the entry starts at.
+    line: usize,
+    msgctxt: Option<String>,
+    msgid: String,
+    msgstr: String,
+    fuzzy: bool,
+    obsolete: bool,
+}
+
+/// The field of an entry a continued string is appended to.
+#[derive(PartialEq, Clone, Copy)]
+enum Field {
+    None,
+    Context,
+    Id,
+    Str,
+    /// A field that is read but not imported, like `msgid_plural`.
+    Ignored,
+}
+
+/// Parses the entries of a PO file.
+fn parse_entries(src: &str) -> LingResult<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut entry: Option<Entry> = None;
+    let mut field = Field::None;
+
+    for (i, line) in src.lines().enumerate() {
+        let line_num = i + 1;
+        let mut line = line.trim();
+        let obsolete = line.starts_with("#~");
+        if obsolete {
+            line = line[2..].trim();
+        }
+        if line.starts_with("#,") {
+            // the flags come before the entry they belong to
+            if field == Field::Str || field == Field::Ignored {
+                entries.extend(entry.take());
+                field = Field::None;
+            }
+            let fuzzy = line[2..].split(',').any(|f| f.trim() == "fuzzy");
+            let entry = entry.get_or_insert_with(|| Entry { line: line_num, ..Entry::default() });
+            entry.fuzzy |= fuzzy;
+            continue;
+        }
+        // the comments and the previous strings of the fuzzy entries are skipped
+        if line.starts_with('#') || line.starts_with('|') {
+            continue;
+        }
+        if line.is_empty() {
+            entries.extend(entry.take());
+            field = Field::None;
+            continue;
+        }
+
+        let (keyword, rest) = match line.find(|c: char| c.is_whitespace()) {
+            Some(i) if !line.starts_with('"') => (&line[..i], line[i..].trim()),
+            _ => ("", line),
+        };
+        let text = try!(unquote(rest, line_num));
+        let new_field = match keyword {
+            "" if field == Field::None => {
+                return Err(LingError::ParseError(line_num, "unexpected string".to_owned()));
+            }
+            "" => field,
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgstr" | "msgstr[0]" => Field::Str,
+            "msgid_plural" => Field::Ignored,
+            k if k.starts_with("msgstr[") => Field::Ignored,
+            k => return Err(LingError::ParseError(line_num, format!("unknown keyword `{}`", k))),
+        };
+        // a new entry can start without an empty line between the entries
+        let starts_entry = new_field == Field::Context || new_field == Field::Id;
+        if keyword != "" && starts_entry && (field == Field::Str || field == Field::Ignored) {
+            entries.extend(entry.take());
+        }
+        field = new_field;
+
+        let entry = entry.get_or_insert_with(|| Entry { line: line_num, ..Entry::default() });
+        entry.obsolete |= obsolete;
+        match field {
+            Field::Context => entry.msgctxt.get_or_insert_with(String::new).push_str(&text),
+            Field::Id => entry.msgid.push_str(&text),
+            Field::Str => entry.msgstr.push_str(&text),
+            _ => (),
+        }
+    }
+    entries.extend(entry);
+    Ok(entries)
+}
+
+/// Escapes a string for writing it in quotes.
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reads a quoted string.
+fn unquote(s: &str, line_num: usize) -> LingResult<String> {
+    let err = |msg: &str| Err(LingError::ParseError(line_num, msg.to_owned()));
+    if s.len() < 2 || !s.starts_with('"') || !s.ends_with('"') {
+        return err("expected a quoted string");
+    }
+    let mut out = String::new();
+    let mut chars = s[1..s.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    _ => return err("invalid escape"),
+                }
+            }
+            '"' => return err("unescaped quote"),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
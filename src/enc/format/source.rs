@@ -0,0 +1,405 @@
+//! Contains the reader and writer for the EncSys encyclopedia source format.
+//!
+//! A source file is a list of words. A word starts with an unindented line containing it's name
+//! followed by a colon, and every indented line after it contains one tag of the word.
+//! A tag line starts with the name of the `LingTag` variant followed by it's fields separated by
+//! whitespace. Fields containing whitespace or quotes can be written in double quotes, with `\"`,
+//! `\\`, `\n` and `\r` as escapes. Empty lines and lines starting with `#` are ignored, so
+//! a word name starting with `#` has to be quoted.
+//!
+//! The typed values of the `Value` tags are parsed with `TagValue::parse`, except for the quoted
+//! ones which are always strings. A `ValueList` line creates a `Value` tag with a list of values,
+//! and the nested lists are written between `[` and `]`, which are separated from the values
+//! with whitespace.
+//!
+//! The format has no syntax for the `LingTag::User` tags, so it's only available for the words
+//! and encyclopedias without user-defined tags, `Word` and `Encyclopedia`.
+//!
+//! ```text
+//! # a small monster
+//! goblin:
+//!     Type creature
+//!     Parent monster
+//!     HasWords arm leg
+//!     Relation antonym elf
+//!     CustomStr hp 10
+//!     CustomVec drops bone "rusty club"
+//!     Value speed 1.5
+//!     Value damage 2..5
+//!     ValueList weaknesses "fire" "holy"
+//!     ValueList attacks [ "bite" 1..2 ] [ "claw" 2..4 ]
+//!     Remove Custom green
+//! ```
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::provenance::Provenance;
+use enc::word::Word;
+use enc::ling::{LingTag, TagValue, UserTag};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// A word parsed from encyclopedia source text with the lines it was defined at.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SourceWord {
+    pub word: Word<'static>,
+    /// The line with the name of the word.
+    pub line: usize,
+    /// The tags of the word and the lines they were defined at.
+    pub tag_lines: Vec<(LingTag, usize)>,
+}
+
+/// Parses the words from encyclopedia source text.
+pub fn parse_words(src: &str) -> LingResult<Vec<Word<'static>>> {
+    parse_source_words(src).map(|words| words.into_iter().map(|w| w.word).collect())
+}
+
+/// Parses the words from encyclopedia source text with their line numbers.
+pub fn parse_source_words(src: &str) -> LingResult<Vec<SourceWord>> {
+    let mut words: Vec<SourceWord> = Vec::new();
+    let mut names = HashSet::new();
+
+    for (i, line) in src.lines().enumerate() {
+        let line_num = i + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            // a new word
+            let name = try!(parse_word_header(trimmed, line_num));
+            if !names.insert(name.clone()) {
+                return Err(LingError::ParseError(line_num, format!("duplicate word `{}`", name)));
+            }
+            words.push(SourceWord {
+                word: Word::new(name),
+                line: line_num,
+                tag_lines: Vec::new(),
+            });
+        } else {
+            let tokens = try!(tokenize(trimmed, line_num));
+            let tag = try!(parse_tag(&tokens, line_num));
+            match words.last_mut() {
+                Some(word) => {
+                    if let Some(key) = tag.key() {
+                        if word.word.has_key(key) {
+                            return Err(LingError::ParseError(line_num,
+                                                             format!("duplicate key `{}`", key)));
+                        }
+                    }
+                    word.tag_lines.push((tag.clone(), line_num));
+                    word.word.add_tag(tag.into())
+                }
+                None => {
+                    return Err(LingError::ParseError(line_num, "tag outside of a word".to_owned()))
+                }
+            }
+        }
+    }
+    Ok(words)
+}
+
+/// Reads and parses the words from an encyclopedia source file.
+pub fn read_words<P: AsRef<Path>>(path: P) -> LingResult<Vec<Word<'static>>> {
+    read_source_words(path).map(|words| words.into_iter().map(|w| w.word).collect())
+}
+
+/// Reads and parses the words from an encyclopedia source file with their line numbers.
+pub fn read_source_words<P: AsRef<Path>>(path: P) -> LingResult<Vec<SourceWord>> {
+    let mut src = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut src));
+    parse_source_words(&src)
+}
+
+/// Records the file and the lines of the tags of the words as their provenance in the
+/// encyclopedia, with the current layer of the encyclopedia.
+///
+/// Does nothing if the encyclopedia isn't tracking provenance, see
+/// `Encyclopedia::enable_provenance`. The words should have been added to the encyclopedia.
+pub fn record_provenance<U: UserTag>(enc: &mut GenericEncyclopedia<U>,
+                                     path: &Path,
+                                     words: &[SourceWord]) {
+    if !enc.is_provenance_enabled() {
+        return;
+    }
+    let layer = enc.get_layer().map(ToOwned::to_owned);
+    for word in words {
+        let name = word.word.get_name();
+        for &(ref tag, line) in &word.tag_lines {
+            let provenance = Provenance {
+                file: Some(path.to_owned()),
+                line: Some(line),
+                layer: layer.clone(),
+            };
+            // the words that weren't added are skipped
+            let _ = enc.set_tag_provenance(&name, &tag.clone().with_user_tag(), provenance);
+        }
+    }
+}
+
+/// Writes the words into encyclopedia source text.
+///
+/// The tags of each word are sorted by their textual presentation, so the same words always
+/// produce the same text.
+pub fn write_words<'a, I>(words: I) -> String
+    where I: IntoIterator<Item = Word<'a>>
+{
+    let mut out = String::new();
+    for word in words {
+        let mut tags: Vec<String> = word.iter().map(|t| write_tag(&t)).collect();
+        tags.sort();
+
+        // writing into a string never fails
+        writeln!(out, "{}:", quote(&word.get_name())).unwrap();
+        for tag in tags {
+            writeln!(out, "    {}", tag).unwrap();
+        }
+    }
+    out
+}
+
+/// Returns the source presentation of a single tag.
+///
+/// Only the tags without user-defined tags can be written, since `NoUserTag` has no values that
+/// would need a presentation.
+pub fn write_tag(tag: &LingTag) -> String {
+    let quote_all = |v: &mut dyn Iterator<Item = &String>| {
+        v.map(|s| quote(s)).collect::<Vec<_>>().join(" ")
+    };
+
+    match tag {
+        &LingTag::Type(ref s) => format!("Type {}", quote(s)),
+        &LingTag::UseTagGroup(ref s) => format!("UseTagGroup {}", quote(s)),
+        &LingTag::Parent(ref s) => format!("Parent {}", quote(s)),
+        &LingTag::HasWords(ref v) => format!("HasWords {}", quote_all(&mut v.iter())),
+        &LingTag::Synonym(ref s) => format!("Synonym {}", quote(s)),
+        &LingTag::Relation(ref r, ref s) => format!("Relation {} {}", quote(r), quote(s)),
+        &LingTag::Custom(ref s) => format!("Custom {}", quote(s)),
+        &LingTag::CustomStr(ref k, None) => format!("CustomStr {}", quote(k)),
+        &LingTag::CustomStr(ref k, Some(ref v)) => format!("CustomStr {} {}", quote(k), quote(v)),
+        &LingTag::CustomVec(ref k, ref v) => {
+            format!("CustomVec {} {}", quote(k), quote_all(&mut v.iter()))
+        }
+        &LingTag::CustomSet(ref k, ref v) => {
+            format!("CustomSet {} {}", quote(k), quote_all(&mut v.iter()))
+        }
+        &LingTag::Value(ref k, ref v @ TagValue::List(_)) => {
+            format!("ValueList {} {}", quote(k), v).trim_end().to_owned()
+        }
+        &LingTag::Value(ref k, ref v) => format!("Value {} {}", quote(k), v),
+        &LingTag::Remove(ref t) => format!("Remove {}", write_tag(t)),
+        &LingTag::User(ref u) => match *u {},
+    }
+}
+
+/// Parses a single tag from it's source presentation, see `write_tag`.
+///
+/// The errors are reported at the given line.
+pub fn read_tag(src: &str, line_num: usize) -> LingResult<LingTag> {
+    let tokens = try!(tokenize(src.trim(), line_num));
+    parse_tag(&tokens, line_num)
+}
+
+/// Quotes the string if it can't be written as a plain token.
+///
+/// The strings starting with `#` are quoted, since a line starting with it is a comment.
+fn quote(s: &str) -> String {
+    if !s.is_empty() && !s.starts_with('#') &&
+       !s.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return s.to_owned();
+    }
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn parse_word_header(line: &str, line_num: usize) -> LingResult<String> {
+    if !line.ends_with(':') {
+        return Err(LingError::ParseError(line_num, "expected `:` after the word name".to_owned()));
+    }
+    let tokens = try!(tokenize(&line[..line.len() - 1], line_num));
+    if tokens.len() != 1 {
+        return Err(LingError::ParseError(line_num, "expected a single word name".to_owned()));
+    }
+    Ok(tokens.into_iter().next().unwrap().text)
+}
+
+/// A whitespace separated part of a line.
+struct Token {
+    text: String,
+    /// True if the token was written in quotes.
+    quoted: bool,
+}
+
+/// Splits a line into whitespace separated tokens, handling the quoted ones.
+fn tokenize(line: &str, line_num: usize) -> LingResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut token = String::new();
+        let quoted = chars.peek() == Some(&'"');
+        match chars.peek() {
+            None => break,
+            Some(&'"') => {
+                chars.next();
+                let unterminated = || {
+                    Err(LingError::ParseError(line_num, "unterminated quote".to_owned()))
+                };
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            match chars.next() {
+                                Some('n') => token.push('\n'),
+                                Some('r') => token.push('\r'),
+                                Some(c) => token.push(c),
+                                None => return unterminated(),
+                            }
+                        }
+                        Some(c) => token.push(c),
+                        None => return unterminated(),
+                    }
+                }
+            }
+            Some(_) => {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+            }
+        }
+        tokens.push(Token {
+            text: token,
+            quoted: quoted,
+        });
+    }
+    Ok(tokens)
+}
+
+/// Parses a typed value from a token.
+fn parse_value(token: &Token, line_num: usize) -> LingResult<TagValue> {
+    if token.quoted {
+        return Ok(TagValue::Str(token.text.clone()));
+    }
+    TagValue::parse(&token.text).map_err(|msg| LingError::ParseError(line_num, msg))
+}
+
+/// Parses the values of a list, with the nested lists in `[` and `]` tokens.
+fn parse_list(tokens: &[Token], line_num: usize) -> LingResult<Vec<TagValue>> {
+    let err = |msg: &str| Err(LingError::ParseError(line_num, msg.to_owned()));
+
+    // the lists that are still open, the outermost first
+    let mut lists = vec![Vec::new()];
+    for token in tokens {
+        match (&token.text as &str, token.quoted) {
+            ("[", false) => lists.push(Vec::new()),
+            ("]", false) => {
+                if lists.len() == 1 {
+                    return err("unmatched `]`");
+                }
+                let list = lists.pop().unwrap();
+                lists.last_mut().unwrap().push(TagValue::List(list));
+            }
+            _ => {
+                let value = try!(parse_value(token, line_num));
+                lists.last_mut().unwrap().push(value);
+            }
+        }
+    }
+    if lists.len() > 1 {
+        return err("unclosed `[`");
+    }
+    Ok(lists.pop().unwrap())
+}
+
+/// Parses a tag from the tokens of a tag line.
+fn parse_tag(tokens: &[Token], line_num: usize) -> LingResult<LingTag> {
+    let err = |msg: String| Err(LingError::ParseError(line_num, msg));
+
+    let (kind, arg_tokens) = match tokens.split_first() {
+        Some((kind, args)) => (&kind.text as &str, args),
+        None => return err("expected a tag".to_owned()),
+    };
+    let args: Vec<String> = arg_tokens.iter().map(|t| t.text.clone()).collect();
+
+    // checks the amount of arguments for the tag kinds with a fixed amount of them
+    let expect_args = |min: usize, max: usize| if args.len() < min || args.len() > max {
+        Err(LingError::ParseError(line_num,
+                                  format!("wrong amount of arguments for `{}`", kind)))
+    } else {
+        Ok(())
+    };
+
+    Ok(match kind {
+        "Type" => {
+            try!(expect_args(1, 1));
+            LingTag::Type(args[0].clone())
+        }
+        "UseTagGroup" => {
+            try!(expect_args(1, 1));
+            LingTag::UseTagGroup(args[0].clone())
+        }
+        "Parent" => {
+            try!(expect_args(1, 1));
+            LingTag::Parent(args[0].clone())
+        }
+        "HasWords" => LingTag::HasWords(args.to_vec()),
+        "Synonym" => {
+            try!(expect_args(1, 1));
+            LingTag::Synonym(args[0].clone())
+        }
+        "Relation" => {
+            try!(expect_args(2, 2));
+            LingTag::Relation(args[0].clone(), args[1].clone())
+        }
+        "Custom" => {
+            try!(expect_args(1, 1));
+            LingTag::Custom(args[0].clone())
+        }
+        "CustomStr" => {
+            try!(expect_args(1, 2));
+            LingTag::CustomStr(args[0].clone(), args.get(1).cloned())
+        }
+        "CustomVec" => {
+            try!(expect_args(1, usize::max_value()));
+            LingTag::CustomVec(args[0].clone(), args[1..].to_vec())
+        }
+        "CustomSet" => {
+            try!(expect_args(1, usize::max_value()));
+            LingTag::CustomSet(args[0].clone(), args[1..].iter().cloned().collect::<BTreeSet<_>>())
+        }
+        "Value" => {
+            try!(expect_args(2, 2));
+            LingTag::Value(args[0].clone(), try!(parse_value(&arg_tokens[1], line_num)))
+        }
+        "ValueList" => {
+            try!(expect_args(1, usize::max_value()));
+            let values = try!(parse_list(&arg_tokens[1..], line_num));
+            LingTag::Value(args[0].clone(), TagValue::List(values))
+        }
+        "Remove" => LingTag::Remove(Box::new(try!(parse_tag(arg_tokens, line_num)))),
+        _ => return err(format!("unknown tag `{}`", kind)),
+    })
+}
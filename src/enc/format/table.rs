@@ -0,0 +1,371 @@
+//! Contains the reader and writer for the CSV and TSV tables used by the translators.
+//!
+//! A table has a row for every word. The first row is the header, and the first column of it
+//! has to be `name`. The column named `tags` has the tags of the words in the source format,
+//! one tag per line, see `source::write_tag`. Every other column is a text column, like a
+//! locale name or a form name, and the texts of a word are stored in it's `LingTag::CustomStr`
+//! tags with the column names as the keys. The `LingTag::User` tags have no source format, so
+//! they aren't written into the `tags` column and the imported rows keep them.
+//!
+//! A table can have any of the text columns, so an imported row only changes the texts in it's
+//! columns and keeps the other texts of the word. The tags in the `tags` column are added to the
+//! other tags of the word unless they are imported with `TagImport::Replace`.
+//!
+//! The fields containing the delimiter, quotes, line breaks or leading or trailing whitespace
+//! are written in double quotes, and the quotes inside them are doubled, like in RFC 4180.
+//!
+//! ```text
+//! name,en,fi,tags
+//! goblin,goblin,peikko,"Type creature
+//! Parent monster"
+//! ```
+use std::collections::HashSet;
+use std::mem;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::GenericWord;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::error::LingError;
+use enc::format::source::{read_tag, write_tag};
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The name of the column with the word names.
+pub const NAME_COLUMN: &'static str = "name";
+/// The name of the column with the other tags of the words.
+pub const TAGS_COLUMN: &'static str = "tags";
+
+/// The format of a table file.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TableFormat {
+    /// Comma separated values.
+    Csv,
+    /// Tab separated values.
+    Tsv,
+}
+
+impl TableFormat {
+    fn delimiter(&self) -> char {
+        match *self {
+            TableFormat::Csv => ',',
+            TableFormat::Tsv => '\t',
+        }
+    }
+}
+
+/// How the tags in the `tags` column of an imported table change the existing words.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TagImport {
+    /// The tags are added to the tags of the words, replacing the custom tags with the same keys.
+    Merge,
+    /// The tags replace the tags of the words, except for the texts, which are the
+    /// `LingTag::CustomStr` tags with a value, and the `LingTag::User` tags.
+    Replace,
+}
+
+/// A text missing from a row of an imported table.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct MissingText {
+    /// The line the row starts at.
+    pub line: usize,
+    pub word: String,
+    pub column: String,
+}
+
+/// Tells what was changed by importing a table.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct TableReport {
+    /// The names of the added words.
+    pub added: Vec<String>,
+    /// The names of the existing words whose tags were changed.
+    pub updated: Vec<String>,
+    /// The empty text fields. The existing texts of the words are kept for them.
+    pub missing: Vec<MissingText>,
+    /// The rows that were skipped, with the lines they start at and the reasons.
+    pub malformed: Vec<(usize, String)>,
+}
+
+/// Writes the words of the encyclopedia into a table with the given text columns.
+///
+/// The words are sorted by name. If `with_tags` is true, the table has a `tags` column with
+/// the tags that aren't written in the text columns, sorted like in `source::write_words`.
+pub fn write_table<U: UserTag, T: AsRef<str>>(enc: &GenericEncyclopedia<U>,
+                                              columns: &[T],
+                                              with_tags: bool,
+                                              format: TableFormat)
+                                              -> String {
+    let delimiter = format.delimiter();
+    let mut header = vec![NAME_COLUMN];
+    header.extend(columns.iter().map(|c| c.as_ref()));
+    if with_tags {
+        header.push(TAGS_COLUMN);
+    }
+    let mut out = write_row(&header, delimiter);
+
+    let mut words: Vec<GenericWord<U>> = enc.iter_words().collect();
+    words.sort_by(|a, b| a.get_name().cmp(&b.get_name()));
+    for word in words {
+        let name = word.get_name();
+        let mut row = vec![name.to_string()];
+        for column in columns {
+            row.push(word.get_custom_str(column.as_ref()).unwrap_or("").to_owned());
+        }
+        if with_tags {
+            let mut tags: Vec<String> = word.get_tags()
+                .iter()
+                .filter(|t| !is_text_tag(t, columns))
+                .filter_map(|t| t.without_user_tag())
+                .map(|t| write_tag(&t))
+                .collect();
+            tags.sort();
+            row.push(tags.join("\n"));
+        }
+        out.push_str(&write_row(&row, delimiter));
+    }
+    out
+}
+
+/// Imports a table into the encyclopedia.
+///
+/// The rows of new words add the words, and the rows of existing words update them. The texts
+/// in the text columns replace the texts of the words, and the words keep their texts that
+/// aren't in the table. The tags in the `tags` column are merged into the words or replace their
+/// tags, see `TagImport`. A leading byte order mark is skipped. Every row is completed and checked
+/// with `Encyclopedia::apply_schema`.
+/// The rows that can't be read or checked are skipped and listed in the report.
+///
+/// Fails if the header row isn't valid.
+pub fn import_table<U: UserTag>(enc: &mut GenericEncyclopedia<U>,
+                                src: &str,
+                                format: TableFormat,
+                                tag_import: TagImport)
+                                -> LingResult<TableReport> {
+    let src = if src.starts_with('\u{feff}') {
+        &src['\u{feff}'.len_utf8()..]
+    } else {
+        src
+    };
+    let mut rows = parse_rows(src, format.delimiter()).into_iter();
+    let header = match rows.next() {
+        Some(Ok((_, header))) => header,
+        Some(Err((line, msg))) => return Err(LingError::ParseError(line, msg)),
+        None => return Err(LingError::ParseError(1, "missing header row".to_owned())),
+    };
+    if header.first().map(|h| h as &str) != Some(NAME_COLUMN) {
+        let msg = format!("the first column must be `{}`", NAME_COLUMN);
+        return Err(LingError::ParseError(1, msg));
+    }
+    let mut seen = HashSet::new();
+    if let Some(c) = header.iter().find(|c| c.is_empty() || !seen.insert(*c)) {
+        return Err(LingError::ParseError(1, format!("invalid column name `{}`", c)));
+    }
+    let tags_index = header.iter().position(|c| c == TAGS_COLUMN);
+    let text_columns: Vec<(usize, &String)> = header.iter()
+        .enumerate()
+        .skip(1)
+        .filter(|&(i, _)| Some(i) != tags_index)
+        .collect();
+
+    let mut report = TableReport::default();
+    for row in rows {
+        let (line, fields) = match row {
+            Ok(row) => row,
+            Err(e) => {
+                report.malformed.push(e);
+                continue;
+            }
+        };
+        if fields.len() != header.len() {
+            report.malformed.push((line,
+                                   format!("expected {} fields, found {}",
+                                           header.len(),
+                                           fields.len())));
+            continue;
+        }
+        // the names aren't trimmed, since the writer quotes the names with spaces around them
+        let name = &fields[0] as &str;
+        if name.trim().is_empty() {
+            report.malformed.push((line, "missing word name".to_owned()));
+            continue;
+        }
+
+        let old = enc.get_word(name).map(GenericWord::into_owned);
+        // the row can use an alias of the word
+        let name = old.as_ref().map_or(name.to_owned(), |w| w.get_name().into_owned());
+        let mut word = old.clone().unwrap_or_else(|| GenericWord::new(name.clone()));
+        if let Some(i) = tags_index {
+            let tags = fields[i].lines().filter(|l| !l.trim().is_empty()).map(|l| {
+                read_tag(l, line).map(LingTag::with_user_tag)
+            });
+            let tags = match tags.collect::<LingResult<Vec<LingTag<U>>>>() {
+                Ok(tags) => tags,
+                Err(e) => {
+                    report.malformed.push((line, e.to_string()));
+                    continue;
+                }
+            };
+            if tag_import == TagImport::Replace {
+                // the tags that can't be written in the column are kept
+                let kept: Vec<_> = word.get_tags()
+                    .iter()
+                    .filter(|t| is_text(t) || t.without_user_tag().is_none())
+                    .cloned()
+                    .collect();
+                word = GenericWord::new_from_collection(name.clone(), kept);
+            }
+            for tag in tags {
+                word.add_tag(tag.into());
+            }
+        }
+        for &(i, column) in &text_columns {
+            if fields[i].is_empty() {
+                report.missing.push(MissingText {
+                    line: line,
+                    word: name.clone(),
+                    column: column.clone(),
+                });
+            } else {
+                word.add_tag(LingTag::CustomStr(column.clone(), Some(fields[i].clone())).into());
+            }
+        }
+
+        if let Err(e) = enc.apply_schema(&mut word) {
+            report.malformed.push((line, e.to_string()));
+            continue;
+        }
+        match old {
+            Some(ref old) if old.get_tags() == word.get_tags() => (),
+            Some(_) => {
+                report.updated.push(word.get_name().into_owned());
+                enc.add_word(word);
+            }
+            None => {
+                report.added.push(word.get_name().into_owned());
+                enc.add_word(word);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Returns true if the tag is a text, which is a custom tag with a string value.
+fn is_text<U>(tag: &LingTag<U>) -> bool {
+    match *tag {
+        LingTag::CustomStr(_, Some(_)) => true,
+        _ => false,
+    }
+}
+
+/// Returns true if the tag is written in one of the text columns.
+fn is_text_tag<U, T: AsRef<str>>(tag: &LingTag<U>, columns: &[T]) -> bool {
+    match *tag {
+        LingTag::CustomStr(ref key, Some(_)) => columns.iter().any(|c| c.as_ref() == key),
+        _ => false,
+    }
+}
+
+/// Writes a row, quoting the fields when needed.
+fn write_row<T: AsRef<str>>(fields: &[T], delimiter: char) -> String {
+    let mut out = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        let field = field.as_ref();
+        if i > 0 {
+            out.push(delimiter);
+        }
+        let special = |c| c == delimiter || c == '"' || c == '\n' || c == '\r';
+        let needs_quotes = field.contains(special) || field.trim() != field;
+        if needs_quotes {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// Splits the text into rows of fields, with the lines the rows start at.
+///
+/// A row that can't be read is returned as an error with the line it starts at, and the reading
+/// continues from the next line. Empty lines are skipped.
+fn parse_rows(src: &str, delimiter: char) -> Vec<Result<(usize, Vec<String>), (usize, String)>> {
+    let mut rows = Vec::new();
+    let mut chars = src.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        // true if the current field was quoted, so a quote after it isn't a new quoted field
+        let mut quoted = false;
+        let mut error = None;
+        loop {
+            match chars.next() {
+                None => break,
+                Some('\n') => {
+                    line += 1;
+                    break;
+                }
+                Some('\r') if chars.peek() == Some(&'\n') => (),
+                Some('"') if field.is_empty() && !quoted => {
+                    quoted = true;
+                    // a quoted field continues until a quote that isn't doubled
+                    loop {
+                        match chars.next() {
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                field.push(c);
+                            }
+                            None => {
+                                error = Some("unterminated quote".to_owned());
+                                break;
+                            }
+                        }
+                    }
+                    match chars.peek() {
+                        Some(&c) if c != delimiter && c != '\n' && c != '\r' => {
+                            error = Some("unexpected text after a quoted field".to_owned());
+                        }
+                        _ => (),
+                    }
+                    if error.is_some() {
+                        // skip the rest of the row
+                        while let Some(c) = chars.next() {
+                            if c == '\n' {
+                                line += 1;
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                }
+                Some(c) if c == delimiter => {
+                    fields.push(mem::replace(&mut field, String::new()));
+                    quoted = false;
+                }
+                Some(c) => field.push(c),
+            }
+        }
+
+        match error {
+            Some(msg) => rows.push(Err((start, msg))),
+            None => {
+                fields.push(field);
+                if !(fields.len() == 1 && fields[0].is_empty()) {
+                    rows.push(Ok((start, fields)));
+                }
+            }
+        }
+    }
+    rows
+}
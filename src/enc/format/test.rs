@@ -0,0 +1,441 @@
+use std::f64;
+
+use super::source::*;
+use enc::Word;
+use enc::ling::{LingTag, LingError, TagValue};
+
+#[test]
+fn parse_source() {
+    let src = "# comment\n\
+               goblin:\n\
+               \x20   Type creature\n\
+               \n\
+               \x20   CustomStr hp 10\n\
+               \x20   CustomVec drops bone \"rusty \\\"club\\\"\"\n\
+               \x20   Remove Custom green\n\
+               orc:\n";
+
+    let words = parse_words(src).unwrap();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0],
+               Word::new_from_collection("goblin",
+                                         vec![LingTag::Type("creature".to_owned()),
+                                              LingTag::CustomStr("hp".to_owned(),
+                                                                 Some("10".to_owned())),
+                                              LingTag::CustomVec("drops".to_owned(),
+                                                                 vec!["bone".to_owned(),
+                                                                      "rusty \"club\""
+                                                                          .to_owned()]),
+                                              LingTag::Remove(Box::new(LingTag::Custom("green"
+                                                  .to_owned())))]));
+    assert_eq!(words[1], Word::new("orc"));
+
+    // writing and parsing again gives the same words
+    assert_eq!(parse_words(&write_words(words.clone())).unwrap(), words);
+
+    // names starting with `#` and line breaks are quoted
+    let words = vec![Word::new_from_collection("#1",
+                                               vec![LingTag::CustomStr("text".to_owned(),
+                                                                       Some("a\nb\r".to_owned()))]),
+                     Word::new("#2")];
+    let src = write_words(words.clone());
+    assert_eq!(src.lines().count(), 3);
+    assert_eq!(parse_words(&src).unwrap(), words);
+}
+
+#[test]
+fn parse_errors() {
+    match parse_words("goblin:\n    Type\n") {
+        Err(LingError::ParseError(2, _)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    match parse_words("    Custom tag\n") {
+        Err(LingError::ParseError(1, _)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(parse_words("goblin:\n    Custom \"tag\n").is_err());
+    match parse_words("goblin:\n    Custom \"tag\\\n") {
+        Err(LingError::ParseError(2, ref msg)) if msg == "unterminated quote" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(parse_words("goblin:\ngoblin:\n").is_err());
+}
+
+#[test]
+fn parse_values() {
+    let src = "goblin:\n\
+               \x20   Value hp 10\n\
+               \x20   Value speed 1.5\n\
+               \x20   Value hostile true\n\
+               \x20   Value damage 2..5\n\
+               \x20   Value title \"10\"\n\
+               \x20   ValueList drops \"bone\" 3 0.5..1\n\
+               \x20   ValueList attacks [ \"bite\" [ ] ] 2\n\
+               \x20   Value mana inf\n\
+               \x20   Value luck nan\n\
+               \x20   Value cold -inf..0\n\
+               \x20   Value motto \"first\\nsecond\"\n";
+
+    let words = parse_words(src).unwrap();
+    let goblin = &words[0];
+    assert_eq!(goblin.get_int("hp").unwrap(), Some(10));
+    assert_eq!(goblin.get_float("hp").unwrap(), Some(10.0));
+    assert_eq!(goblin.get_float("speed").unwrap(), Some(1.5));
+    assert_eq!(goblin.get_bool("hostile").unwrap(), Some(true));
+    assert_eq!(goblin.get_int_range("damage").unwrap(), Some((2, 5)));
+    assert_eq!(goblin.get_str("title").unwrap(), Some("10"));
+    assert_eq!(goblin.get_list("drops").unwrap(),
+               Some(&[TagValue::Str("bone".to_owned()),
+                      TagValue::Int(3),
+                      TagValue::FloatRange(0.5, 1.0)][..]));
+    assert_eq!(goblin.get_list("attacks").unwrap(),
+               Some(&[TagValue::List(vec![TagValue::Str("bite".to_owned()),
+                                          TagValue::List(Vec::new())]),
+                      TagValue::Int(2)][..]));
+    assert_eq!(goblin.get_float("mana").unwrap(), Some(f64::INFINITY));
+    assert!(goblin.get_float("luck").unwrap().unwrap().is_nan());
+    assert_eq!(goblin.get_float_range("cold").unwrap(), Some((f64::NEG_INFINITY, 0.0)));
+    assert_eq!(goblin.get_str("motto").unwrap(), Some("first\nsecond"));
+    assert_eq!(goblin.get_int("armor").unwrap(), None);
+    match goblin.get_int("speed") {
+        Err(LingError::WrongValueType(ref key, "int")) if key == "speed" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert_eq!(parse_words(&write_words(words.clone())).unwrap(), words);
+
+    // unquoted strings and malformed numbers are errors
+    match parse_words("goblin:\n    Value hp ten\n") {
+        Err(LingError::ParseError(2, _)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(parse_words("goblin:\n    Value damage 2..x\n").is_err());
+    match parse_words("goblin:\n    Value damage 5..2\n") {
+        Err(LingError::ParseError(2, _)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(parse_words("goblin:\n    Value speed 2.5..-1\n").is_err());
+    assert!(parse_words("goblin:\n    Value damage 2..2\n").is_ok());
+    assert!(parse_words("goblin:\n    Value hp 1 2\n").is_err());
+    assert!(parse_words("goblin:\n    Value hp infinity\n").is_err());
+    assert!(parse_words("goblin:\n    ValueList hp [ 1\n").is_err());
+    assert!(parse_words("goblin:\n    ValueList hp 1 ]\n").is_err());
+
+    // the special floats and the nested lists are written in the same form
+    let value = |v: TagValue| LingTag::Value("v".to_owned(), v);
+    let list = TagValue::List(vec![TagValue::Float(f64::NAN),
+                                   TagValue::List(vec![TagValue::Float(f64::NEG_INFINITY)])]);
+    assert_eq!(write_tag(&value(list.clone())), "ValueList v nan [ -inf ]");
+    assert_eq!(read_tag("ValueList v nan [ -inf ]", 1).unwrap(), value(list));
+    assert_eq!(TagValue::Str("a\r\n".to_owned()).to_string(), "\"a\\r\\n\"");
+}
+
+#[test]
+fn write_dot_graph() {
+    use enc::Encyclopedia;
+    use super::dot::write_dot;
+
+    let mut enc = Encyclopedia::new();
+    enc.add_word(Word::new_from_collection("monster", Vec::<LingTag>::new()));
+    enc.add_word(Word::new_from_collection("goblin",
+                                           vec![LingTag::Parent("monster".to_owned()),
+                                                LingTag::HasWords(vec!["arm".to_owned()])]));
+    assert_eq!(write_dot(&enc),
+               "digraph encyclopedia {\n    rankdir=BT;\n    \"goblin\";\n    \"monster\";\n    \
+                \"goblin\" -> \"monster\";\n    \
+                \"goblin\" -> \"arm\" [style=dashed, label=\"has\"];\n}\n");
+}
+
+#[test]
+fn import_wordnet() {
+    use enc::Encyclopedia;
+    use super::wordnet::{WordNetImport, PartOfSpeech, declare_wordnet_relations};
+
+    let index = "  1 license header\n\
+                 canine n 1 1 ~ 1 0 02083346\n\
+                 dog n 1 2 @ %p 1 0 02084071\n\
+                 domestic_dog n 1 1 @ 1 0 02084071\n\
+                 tail n 1 1 #p 1 0 02157557\n";
+    let data = "  1 license header\n\
+                02083346 05 n 01 canine 0 001 ~ 02084071 n 0000 | a fissiped mammal\n\
+                02084071 05 n 02 dog 0 domestic_dog 0 003 @ 02083346 n 0000 \
+                %p 02157557 n 0000 + 01234567 v 0101 | a member of the genus Canis\n\
+                02157557 05 n 01 tail 0 001 #p 02084071 n 0000 | the posterior part\n";
+    let files = [(PartOfSpeech::Noun, index, data)];
+
+    let words = WordNetImport::new().parse(&files).unwrap();
+    let names: Vec<String> = words.iter().map(|w| w.get_name().into_owned()).collect();
+    assert_eq!(names, vec!["canine.n.01", "dog.n.01", "domestic_dog.n.01", "tail.n.01"]);
+
+    let mut enc = Encyclopedia::new();
+    declare_wordnet_relations(&mut enc);
+    for word in words {
+        enc.check_word(&word).unwrap();
+        enc.add_word(word);
+    }
+    assert!(enc.is_a("dog.n.01", "canine.n.01"));
+    assert_eq!(enc.related("dog.n.01", "part_meronym").unwrap(), vec!["tail.n.01"]);
+    assert!(enc.get_word("domestic_dog.n.01")
+        .unwrap()
+        .has_tag(&LingTag::Synonym("dog.n.01".to_owned())));
+    assert_eq!(enc.get_word("dog.n.01").unwrap().get_custom_str("gloss"),
+               Some("a member of the genus Canis"));
+
+    let words = WordNetImport::new().synsets(vec!["dog.n.01"]).parse(&files).unwrap();
+    assert_eq!(words.len(), 2);
+    assert!(!words[0].get_tags().iter().any(|t| match **t {
+        LingTag::Parent(_) => true,
+        _ => false,
+    }));
+}
+
+#[test]
+fn import_hunspell() {
+    use std::borrow::Cow;
+    use enc::Encyclopedia;
+    use enc::ling::grammeme::{GrammCategory, Grammeme};
+    use super::hunspell::{HunspellImport, HunspellFile};
+
+    let aff = "SET UTF-8\n\
+               TRY esianrtolcdugmphbyfvkwz\n\
+               PFX U Y 1\n\
+               PFX U 0 un .\n\
+               PFX R Y 2\n\
+               PFX R 0 re try\n\
+               PFX R 0 pre tri\n\
+               SFX S Y 2\n\
+               SFX S y ies [^aeiou]y\n\
+               SFX S 0 s [^y]\n\
+               SFX D N 1\n\
+               SFX D 0 d/X e is:past\n";
+    let dic = "3\ncat/SZ\ntry/SR\ntie/USD\n";
+
+    let dict = HunspellImport::new()
+        .map_flag("S", "number", "plural")
+        .map_morph("is:past", "tense", "past")
+        .parse(aff, dic)
+        .unwrap();
+    let features: Vec<(&str, HunspellFile)> =
+        dict.report.unsupported.iter().map(|u| (&u.feature as &str, u.file)).collect();
+    assert_eq!(features,
+               vec![("directive `TRY`", HunspellFile::Affix),
+                    ("continuation classes", HunspellFile::Affix),
+                    ("flag `Z` without an affix class", HunspellFile::Dictionary)]);
+
+    let import = HunspellImport::new()
+        .map_flag("S", "number", "plural")
+        .map_morph("is:past", "tense", "past");
+    // the past class can't be added without the tense category, so nothing is added
+    let mut enc = Encyclopedia::new();
+    enc.add_gramm_cat(GrammCategory::new("number", Some("singular"), vec!["plural"]));
+    assert!(import.parse(aff, dic).unwrap().add_to(&mut enc).is_err());
+    assert_eq!(enc.affix_class_amount(), 0);
+    assert_eq!(enc.word_amount(), 0);
+
+    enc.add_gramm_cat(GrammCategory::new("tense", Some("present"), vec!["past"]));
+    dict.add_to(&mut enc).unwrap();
+
+    let plural = Grammeme::new(Cow::from("number"), Cow::from("plural"));
+    // the conditions of the prefixes are matched against the lemma, not the suffixed form
+    assert_eq!(enc.inflect("try", &[plural.clone()]).unwrap(), vec!["retries", "tries"]);
+    assert_eq!(enc.lemmatize("retries").len(), 1);
+    assert!(enc.lemmatize("pretries").is_empty());
+    assert_eq!(enc.inflect("tie", &[plural.clone()]).unwrap(), vec!["unties", "ties"]);
+
+    let lemmas = enc.lemmatize("tied");
+    assert_eq!(lemmas.len(), 1);
+    assert_eq!(lemmas[0].lemma, "tie");
+    assert_eq!(lemmas[0].grammemes,
+               vec![Grammeme::new(Cow::from("tense"), Cow::from("past"))]);
+    let lemmas = enc.lemmatize("unties");
+    assert_eq!(lemmas.len(), 1);
+    assert_eq!(lemmas[0].grammemes, vec![plural]);
+    assert!(enc.lemmatize("cats").iter().any(|l| l.lemma == "cat"));
+    assert!(enc.lemmatize("dogs").is_empty());
+}
+
+#[test]
+fn translation_tables() {
+    use enc::Encyclopedia;
+    use super::table::{write_table, import_table, TableFormat, TagImport};
+
+    let text = |k: &str, v: &str| LingTag::CustomStr(k.to_owned(), Some(v.to_owned()));
+    let mut enc = Encyclopedia::new();
+    enc.add_word(Word::new_from_collection("goblin",
+                                           vec![text("en", "goblin, \"small\""),
+                                                LingTag::Type("creature".to_owned())]));
+    enc.add_word(Word::new_from_collection("orc", vec![text("en", "orc")]));
+
+    let csv = write_table(&enc, &["en", "fi"], true, TableFormat::Csv);
+    assert_eq!(csv,
+               "name,en,fi,tags\n\
+                goblin,\"goblin, \"\"small\"\"\",,Type creature\n\
+                orc,orc,,\n");
+    let tsv = write_table(&enc, &["en"], false, TableFormat::Tsv);
+    assert_eq!(tsv, "name\ten\ngoblin\t\"goblin, \"\"small\"\"\"\norc\torc\n");
+
+    let src = "name,fi,tags\n\
+               goblin,peikko,\"Type creature\n\
+               Parent monster\"\n\
+               orc,,\n\
+               troll,peikko,Type\n\
+               bad,\"unterminated\"x,\n\
+               elf,haltia\n\
+               dwarf,kääpiö,Custom short\n";
+    let report = import_table(&mut enc, src, TableFormat::Csv, TagImport::Merge).unwrap();
+    assert_eq!(report.added, vec!["dwarf"]);
+    assert_eq!(report.updated, vec!["goblin"]);
+    assert_eq!(report.missing.len(), 1);
+    assert_eq!((report.missing[0].line, &report.missing[0].word as &str), (4, "orc"));
+    let lines: Vec<usize> = report.malformed.iter().map(|m| m.0).collect();
+    assert_eq!(lines, vec![5, 6, 7]);
+
+    let goblin = enc.get_word("goblin").unwrap();
+    assert_eq!(goblin.get_custom_str("fi"), Some("peikko"));
+    assert_eq!(goblin.get_custom_str("en"), Some("goblin, \"small\""));
+    assert!(goblin.has_tag(&LingTag::Type("creature".to_owned())));
+    assert!(goblin.has_tag(&LingTag::Parent("monster".to_owned())));
+    assert_eq!(enc.get_word("orc").unwrap().get_custom_str("en"), Some("orc"));
+
+    let src = "\u{feff}name,tags\ngoblin,Custom big\n";
+    let report = import_table(&mut enc, src, TableFormat::Csv, TagImport::Replace).unwrap();
+    assert_eq!(report.updated, vec!["goblin"]);
+    let goblin = enc.get_word("goblin").unwrap();
+    assert_eq!(goblin.get_custom_str("fi"), Some("peikko"));
+    assert_eq!(goblin.get_custom_str("en"), Some("goblin, \"small\""));
+    assert!(goblin.has_tag(&LingTag::Custom("big".to_owned())));
+    assert_eq!(goblin.tag_amount(), 3);
+
+    let mut padded = Encyclopedia::new();
+    padded.add_word(Word::new_from_collection(" imp ", vec![text("en", "imp")]));
+    let csv = write_table(&padded, &["en"], false, TableFormat::Csv);
+    assert_eq!(csv, "name,en\n\" imp \",imp\n");
+    let report = import_table(&mut padded, &csv, TableFormat::Csv, TagImport::Merge).unwrap();
+    assert!(report.added.is_empty() && report.updated.is_empty());
+    assert_eq!(padded.word_amount(), 1);
+}
+
+#[test]
+fn user_tag_tables() {
+    use enc::{GenericEncyclopedia, GenericWord};
+    use super::table::{write_table, import_table, TableFormat, TagImport};
+    use super::po::write_pot;
+
+    #[derive(PartialEq, Eq, Hash, Clone, Debug)]
+    struct Flying;
+
+    let mut enc: GenericEncyclopedia<Flying> = GenericEncyclopedia::default();
+    enc.add_word(GenericWord::new_from_collection("bat",
+                                                  vec![LingTag::User(Flying),
+                                                       LingTag::Type("creature".to_owned())]));
+    let csv = write_table(&enc, &["fi"], true, TableFormat::Csv);
+    assert_eq!(csv, "name,fi,tags\nbat,,Type creature\n");
+    assert!(write_pot(&enc).contains("msgid \"bat\""));
+
+    let src = "name,fi,tags\nbat,lepakko,Type animal\n";
+    import_table(&mut enc, src, TableFormat::Csv, TagImport::Replace).unwrap();
+    let bat = enc.get_word("bat").unwrap();
+    assert_eq!(bat.get_custom_str("fi"), Some("lepakko"));
+    assert!(bat.has_tag(&LingTag::User(Flying)));
+    assert!(bat.has_tag(&LingTag::Type("animal".to_owned())));
+    assert_eq!(bat.tag_amount(), 3);
+}
+
+#[test]
+fn po_translations() {
+    use std::borrow::Cow;
+    use enc::Encyclopedia;
+    use enc::ling::affix::{AffixClass, AffixKind, AffixRule};
+    use enc::ling::grammeme::{GrammCategory, Grammeme};
+    use enc::{TagSchema, KeySchema, ValueType};
+    use super::po::{write_pot, write_po, import_po, locale_key};
+
+    let mut enc = Encyclopedia::new();
+    enc.add_gramm_cat(GrammCategory::new("number", Some("singular"), vec!["plural"]));
+    let plural = AffixRule::new("", "s", ".").unwrap().with_grammeme("number", "plural");
+    enc.add_affix_class(AffixClass::new("S", AffixKind::Suffix).with_rule(plural)).unwrap();
+    let affixes = LingTag::CustomSet("affixes".to_owned(),
+                                     vec!["S".to_owned()].into_iter().collect());
+    enc.add_word(Word::new_from_collection("goblin", vec![affixes]));
+    enc.add_word(Word::new("orc"));
+    enc.add_word(Word::new("troll"));
+
+    let pot = write_pot(&enc);
+    assert!(pot.contains("msgctxt \"goblin|number=plural\"\nmsgid \"goblins\"\nmsgstr \"\"\n"));
+    assert!(pot.contains("msgctxt \"orc\"\nmsgid \"orc\"\n"));
+    assert!(pot.find("\"goblin\"").unwrap() < pot.find("\"orc\"").unwrap());
+    enc.rename_word("troll", "cave_troll", true).unwrap();
+
+    let src = "msgid \"\"\n\
+               msgstr \"\"\n\
+               \"Language: fi\\n\"\n\
+               \n\
+               msgctxt \"goblin\"\n\
+               msgid \"goblin\"\n\
+               msgstr \"peik\"\n\
+               \"ko\"\n\
+               msgctxt \"goblin|number=plural\"\n\
+               msgid \"goblins\"\n\
+               msgstr \"peikot\"\n\
+               \n\
+               #, fuzzy\n\
+               msgctxt \"orc\"\n\
+               msgid \"orc\"\n\
+               msgstr \"örkki\"\n\
+               \n\
+               msgctxt \"orc|number=plural\"\n\
+               msgid \"orcs\"\n\
+               msgstr \"örkit\"\n\
+               \n\
+               msgctxt \"elf\"\n\
+               msgid \"elf\"\n\
+               msgstr \"\"\n\
+               \n\
+               #~ msgctxt \"elf\"\n\
+               #~ msgid \"troll\"\n\
+               #~ msgstr \"peikko\"\n\
+               \n\
+               msgctxt \"troll\"\n\
+               msgid \"troll\"\n\
+               msgstr \"peikko\"\n";
+    let report = import_po(&mut enc, src, None).unwrap();
+    assert_eq!(report.imported, vec!["goblin", "goblin|number=plural"]);
+    assert_eq!(report.fuzzy, vec!["orc"]);
+    assert_eq!(report.unknown, vec![(18, "orc|number=plural".to_owned())]);
+    assert_eq!(report.untranslated, vec!["elf"]);
+    assert_eq!(report.obsolete, vec!["elf"]);
+    assert_eq!(report.changed, vec![(30, "troll".to_owned())]);
+    assert!(enc.get_word("cave_troll").unwrap().get_custom_str("fi").is_none());
+
+    let plural = Grammeme::new(Cow::from("number"), Cow::from("plural"));
+    let word = enc.get_word("goblin").unwrap();
+    assert_eq!(word.get_custom_str("fi"), Some("peikko"));
+    assert_eq!(word.get_custom_str(&locale_key("fi", &[plural])), Some("peikot"));
+    assert!(write_po(&enc, "fi").contains("msgid \"goblins\"\nmsgstr \"peikot\"\n"));
+
+    let mut schema = TagSchema::new();
+    schema.declare("fi", KeySchema::new(ValueType::Str)).unwrap();
+    enc.set_schema(schema);
+    let src = "msgctxt \"goblin|number=plural\"\n\
+               msgid \"goblins\"\n\
+               msgstr \"hiidet\"\n\
+               \n\
+               msgctxt \"orc\"\n\
+               msgid \"orc\"\n\
+               msgstr \"örkki\"\n";
+    let report = import_po(&mut enc, src, Some("fi")).unwrap();
+    assert_eq!(report.imported, vec!["orc"]);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!((report.rejected[0].0, &report.rejected[0].1 as &str), (1, "goblin|number=plural"));
+
+    // the `|` of a name is escaped, so it isn't read as the start of the grammemes
+    enc.add_word(Word::new("a|b"));
+    assert!(write_pot(&enc).contains("msgctxt \"a\\\\|b\"\nmsgid \"a|b\"\n"));
+    let src = "msgctxt \"a\\\\|b\"\nmsgid \"a|b\"\nmsgstr \"x\"\n\n\
+               msgctxt \"a|b\"\nmsgid \"a|b\"\nmsgstr \"y\"\n";
+    let report = import_po(&mut enc, src, Some("fi")).unwrap();
+    assert_eq!(report.imported, vec!["a\\|b"]);
+    assert_eq!(report.unknown, vec![(5, "a|b".to_owned())]);
+    assert_eq!(enc.get_word("a|b").unwrap().get_custom_str("fi"), Some("x"));
+    assert!(import_po(&mut enc, "msgid \"x\"\nmsgstr", Some("fi")).is_err());
+}
@@ -0,0 +1,401 @@
+//! Contains the importer for the WordNet lexical database files.
+//!
+//! The importer reads the `index.*` and `data.*` files of the WordNet database directory. Every
+//! synset becomes a word named after the sense of it's first lemma in the style
+//! `lemma.pos.number`, like `dog.n.01`, where the number is the sense number from the index file
+//! and the part of speech is one of `n`, `v`, `a` and `r`. The word gets the tags:
+//!
+//! - `Type` with the part of speech, like `Type noun`.
+//! - `Parent` for every hypernym and instance hypernym.
+//! - `Relation` for the other pointers, see `declare_wordnet_relations` for the relation types.
+//!   The hyponyms aren't imported, since they are the children of the synset.
+//! - `CustomVec lemmas` with the lemmas of the synset.
+//! - `CustomStr gloss` with the definition and the examples, if the synset has them.
+//!
+//! The other lemmas of the synset become words of their own, like `domestic_dog.n.01`, with the
+//! `Type` tag and a `Synonym` tag referring to the synset.
+//!
+//! The pointers between lemmas are imported as relations between the synsets, and the pointers
+//! to the synsets that aren't imported are skipped.
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::Word;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The WordNet pointer symbols, the relation types they are imported as and the inverses of the
+/// relation types.
+const RELATIONS: &'static [(&'static str, &'static str, Option<&'static str>)] = &[
+    ("!", "antonym", Some("antonym")),
+    ("#m", "member_holonym", Some("member_meronym")),
+    ("#s", "substance_holonym", Some("substance_meronym")),
+    ("#p", "part_holonym", Some("part_meronym")),
+    ("%m", "member_meronym", Some("member_holonym")),
+    ("%s", "substance_meronym", Some("substance_holonym")),
+    ("%p", "part_meronym", Some("part_holonym")),
+    ("=", "attribute", Some("attribute")),
+    ("+", "derivation", Some("derivation")),
+    (";c", "domain_topic", Some("domain_topic_member")),
+    ("-c", "domain_topic_member", Some("domain_topic")),
+    (";r", "domain_region", Some("domain_region_member")),
+    ("-r", "domain_region_member", Some("domain_region")),
+    (";u", "domain_usage", Some("domain_usage_member")),
+    ("-u", "domain_usage_member", Some("domain_usage")),
+    ("*", "entailment", None),
+    (">", "cause", None),
+    ("^", "also_see", Some("also_see")),
+    ("$", "verb_group", Some("verb_group")),
+    ("&", "similar_to", Some("similar_to")),
+    ("<", "participle", None),
+    ("\\", "pertainym", None),
+];
+
+/// Declares the relation types the WordNet importer uses with their inverses.
+///
+/// The relation types have to be declared before adding the imported words to the encyclopedia
+/// with checks, see `Encyclopedia::check_word`.
+pub fn declare_wordnet_relations<U: UserTag>(enc: &mut GenericEncyclopedia<U>) {
+    for &(_, name, inverse) in RELATIONS {
+        match inverse {
+            Some(inverse) => enc.declare_inverse_relations(name, inverse),
+            None => enc.declare_relation(name),
+        }
+    }
+}
+
+/// A part of speech of the WordNet database.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum PartOfSpeech {
+    Noun,
+    Verb,
+    /// An adjective, including the satellite adjectives.
+    Adjective,
+    Adverb,
+}
+
+impl PartOfSpeech {
+    /// Returns the name of the part of speech, which is used in the `Type` tags.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PartOfSpeech::Noun => "noun",
+            PartOfSpeech::Verb => "verb",
+            PartOfSpeech::Adjective => "adjective",
+            PartOfSpeech::Adverb => "adverb",
+        }
+    }
+
+    /// Returns the letter used for the part of speech in the word names.
+    pub fn letter(&self) -> char {
+        match *self {
+            PartOfSpeech::Noun => 'n',
+            PartOfSpeech::Verb => 'v',
+            PartOfSpeech::Adjective => 'a',
+            PartOfSpeech::Adverb => 'r',
+        }
+    }
+
+    /// Returns the extension of the database files of the part of speech.
+    pub fn file_suffix(&self) -> &'static str {
+        match *self {
+            PartOfSpeech::Noun => "noun",
+            PartOfSpeech::Verb => "verb",
+            PartOfSpeech::Adjective => "adj",
+            PartOfSpeech::Adverb => "adv",
+        }
+    }
+
+    fn from_letter(s: &str) -> Option<PartOfSpeech> {
+        match s {
+            "n" => Some(PartOfSpeech::Noun),
+            "v" => Some(PartOfSpeech::Verb),
+            "a" | "s" => Some(PartOfSpeech::Adjective),
+            "r" => Some(PartOfSpeech::Adverb),
+            _ => None,
+        }
+    }
+
+    fn all() -> Vec<PartOfSpeech> {
+        vec![PartOfSpeech::Noun, PartOfSpeech::Verb, PartOfSpeech::Adjective, PartOfSpeech::Adverb]
+    }
+}
+
+/// A synset read from a data file.
+struct Synset {
+    pos: PartOfSpeech,
+    offset: u32,
+    lemmas: Vec<String>,
+    /// The pointer symbols and the parts of speech and offsets of the target synsets.
+    pointers: Vec<(String, PartOfSpeech, u32)>,
+    gloss: String,
+}
+
+/// An importer for the WordNet database files, with the options for choosing the imported
+/// synsets.
+#[derive(Clone, Debug)]
+pub struct WordNetImport {
+    parts: Vec<PartOfSpeech>,
+    synsets: Option<HashSet<String>>,
+}
+
+impl WordNetImport {
+    /// Creates an importer that imports every synset of every part of speech.
+    pub fn new() -> Self {
+        WordNetImport {
+            parts: PartOfSpeech::all(),
+            synsets: None,
+        }
+    }
+
+    /// Imports only the given parts of speech.
+    ///
+    /// The pointers to the synsets of the other parts of speech are skipped.
+    pub fn parts_of_speech(mut self, parts: &[PartOfSpeech]) -> Self {
+        self.parts = parts.to_vec();
+        self
+    }
+
+    /// Imports only the synsets with the given names, like `dog.n.01`.
+    ///
+    /// The other lemmas of the synsets are imported too, but the pointers to the other synsets
+    /// are skipped.
+    pub fn synsets<I, T>(mut self, names: I) -> Self
+        where I: IntoIterator<Item = T>,
+              T: Into<String>
+    {
+        self.synsets = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Reads the index and data files of the chosen parts of speech from the WordNet database
+    /// directory and imports the words from them.
+    pub fn read_dir<P: AsRef<Path>>(&self, dir: P) -> LingResult<Vec<Word<'static>>> {
+        let read = |name: String| -> LingResult<String> {
+            let mut src = String::new();
+            try!(try!(File::open(dir.as_ref().join(name))).read_to_string(&mut src));
+            Ok(src)
+        };
+        let mut files = Vec::new();
+        for &part in &self.parts {
+            let index = try!(read(format!("index.{}", part.file_suffix())));
+            let data = try!(read(format!("data.{}", part.file_suffix())));
+            files.push((part, index, data));
+        }
+        let files: Vec<(PartOfSpeech, &str, &str)> = files.iter()
+            .map(|&(part, ref index, ref data)| (part, index as &str, data as &str))
+            .collect();
+        self.parse(&files)
+    }
+
+    /// Imports the words from the contents of the index and data files of the given parts of
+    /// speech.
+    ///
+    /// The words are returned in the order of the files, and the words of a file are in the
+    /// order of the synsets.
+    pub fn parse(&self,
+                 files: &[(PartOfSpeech, &str, &str)])
+                 -> LingResult<Vec<Word<'static>>> {
+        let mut senses: HashMap<(PartOfSpeech, String, u32), usize> = HashMap::new();
+        let mut synsets = Vec::new();
+        for &(part, index, data) in files.iter().filter(|f| self.parts.contains(&f.0)) {
+            try!(parse_index(part, index, &mut senses));
+            synsets.extend(try!(parse_data(part, data)));
+        }
+
+        // the names of the synsets are needed for the pointers, so they are all named first
+        let mut names = HashMap::new();
+        for synset in &synsets {
+            let name = try!(sense_name(&senses, synset.pos, &synset.lemmas[0], synset.offset));
+            names.insert((synset.pos, synset.offset), name);
+        }
+        if let Some(ref chosen) = self.synsets {
+            names.retain(|_, name| chosen.contains(name));
+        }
+
+        let mut words = Vec::new();
+        for synset in &synsets {
+            let name = match names.get(&(synset.pos, synset.offset)) {
+                Some(name) => name.clone(),
+                None => continue,
+            };
+            let mut tags = vec![LingTag::Type(synset.pos.name().to_owned()),
+                                LingTag::CustomVec("lemmas".to_owned(), synset.lemmas.clone())];
+            if !synset.gloss.is_empty() {
+                tags.push(LingTag::CustomStr("gloss".to_owned(), Some(synset.gloss.clone())));
+            }
+            for &(ref symbol, pos, offset) in &synset.pointers {
+                let target = match names.get(&(pos, offset)) {
+                    Some(target) => target.clone(),
+                    None => continue,
+                };
+                match &symbol as &str {
+                    "@" | "@i" => tags.push(LingTag::Parent(target)),
+                    s => {
+                        if let Some(&(_, relation, _)) = RELATIONS.iter().find(|r| r.0 == s) {
+                            tags.push(LingTag::Relation(relation.to_owned(), target));
+                        }
+                    }
+                }
+            }
+
+            words.push(Word::new_from_collection(name.clone(), tags));
+
+            for lemma in &synset.lemmas[1..] {
+                let lemma_name = try!(sense_name(&senses, synset.pos, lemma, synset.offset));
+                let tags = vec![LingTag::Type(synset.pos.name().to_owned()),
+                                LingTag::Synonym(name.clone())];
+                words.push(Word::new_from_collection(lemma_name, tags));
+            }
+        }
+        Ok(words)
+    }
+}
+
+impl Default for WordNetImport {
+    fn default() -> Self {
+        WordNetImport::new()
+    }
+}
+
+/// Returns the name of the sense of the lemma in the synset.
+fn sense_name(senses: &HashMap<(PartOfSpeech, String, u32), usize>,
+              pos: PartOfSpeech,
+              lemma: &str,
+              offset: u32)
+              -> LingResult<String> {
+    let lemma = lemma.to_lowercase();
+    match senses.get(&(pos, lemma.clone(), offset)) {
+        Some(sense) => Ok(format!("{}.{}.{:02}", lemma, pos.letter(), sense)),
+        None => {
+            Err(LingError::ParseError(0,
+                                      format!("the sense of `{}` in synset {:08} isn't indexed",
+                                              lemma,
+                                              offset)))
+        }
+    }
+}
+
+/// Returns the lines of a database file that aren't part of the license header, with their
+/// line numbers.
+fn content_lines(src: &str) -> Vec<(usize, &str)> {
+    src.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.starts_with(' ') && !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line))
+        .collect()
+}
+
+/// Parses an index file into the sense numbers of the lemmas in the synsets.
+fn parse_index(part: PartOfSpeech,
+               src: &str,
+               senses: &mut HashMap<(PartOfSpeech, String, u32), usize>)
+               -> LingResult<()> {
+    for (line_num, line) in content_lines(src) {
+        let mut fields = Fields::new(line, line_num);
+        let lemma = try!(fields.next()).to_owned();
+        try!(fields.next());
+        try!(fields.number(10));
+        let pointer_count = try!(fields.number(10));
+        for _ in 0..pointer_count {
+            try!(fields.next());
+        }
+        try!(fields.number(10));
+        try!(fields.number(10));
+        let mut sense = 1;
+        while let Some(offset) = fields.next_offset() {
+            senses.insert((part, lemma.clone(), try!(offset)), sense);
+            sense += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Parses the synsets of a data file.
+fn parse_data(part: PartOfSpeech, src: &str) -> LingResult<Vec<Synset>> {
+    let mut synsets = Vec::new();
+    for (line_num, line) in content_lines(src) {
+        let (line, gloss) = match line.find('|') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+        let mut fields = Fields::new(line, line_num);
+        let offset = try!(fields.number(10));
+        try!(fields.next());
+        try!(fields.next());
+        let lemma_count = try!(fields.number(16));
+        let mut lemmas = Vec::new();
+        for _ in 0..lemma_count {
+            let lemma = try!(fields.next());
+            // adjectives can have a syntactic marker like `(a)` after them
+            let lemma = match lemma.find('(') {
+                Some(i) => &lemma[..i],
+                None => lemma,
+            };
+            lemmas.push(lemma.to_owned());
+            try!(fields.next());
+        }
+        if lemmas.is_empty() {
+            return Err(LingError::ParseError(line_num, "a synset without lemmas".to_owned()));
+        }
+        let pointer_count = try!(fields.number(10));
+        let mut pointers = Vec::new();
+        for _ in 0..pointer_count {
+            let symbol = try!(fields.next()).to_owned();
+            let target = try!(fields.number(10));
+            let pos = try!(fields.next());
+            let pos = try!(PartOfSpeech::from_letter(pos).ok_or_else(|| {
+                LingError::ParseError(line_num, format!("unknown part of speech `{}`", pos))
+            }));
+            try!(fields.next());
+            pointers.push((symbol, pos, target as u32));
+        }
+        synsets.push(Synset {
+            pos: part,
+            offset: offset as u32,
+            lemmas: lemmas,
+            pointers: pointers,
+            gloss: gloss.to_owned(),
+        });
+    }
+    Ok(synsets)
+}
+
+/// The whitespace separated fields of a database file line.
+struct Fields<'a> {
+    iter: ::std::str::SplitWhitespace<'a>,
+    line_num: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(line: &'a str, line_num: usize) -> Self {
+        Fields {
+            iter: line.split_whitespace(),
+            line_num: line_num,
+        }
+    }
+
+    fn next(&mut self) -> LingResult<&'a str> {
+        self.iter.next().ok_or(LingError::ParseError(self.line_num, "missing field".to_owned()))
+    }
+
+    fn number(&mut self, radix: u32) -> LingResult<u64> {
+        let field = try!(self.next());
+        let line_num = self.line_num;
+        u64::from_str_radix(field, radix)
+            .map_err(|_| LingError::ParseError(line_num, format!("invalid number `{}`", field)))
+    }
+
+    /// Returns the next synset offset, or `None` at the end of the line.
+    fn next_offset(&mut self) -> Option<LingResult<u32>> {
+        let line_num = self.line_num;
+        self.iter.next().map(|field| {
+            field.parse()
+                .map_err(|_| LingError::ParseError(line_num, format!("invalid offset `{}`", field)))
+        })
+    }
+}
@@ -0,0 +1,195 @@
+//! Contains the queries to the graph the words form with their `LingTag::Parent` tags.
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::GenericWord;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The taxonomy of the words.
+///
+/// A word is a child of the words it names with `LingTag::Parent`. Only the stored words are part
+/// of the graph, so the parents that aren't stored are ignored like in `resolve_tags`. Aliases
+/// are accepted everywhere a name is, but the returned names are always the real names.
+/// The returned lists are sorted so that the same encyclopedia always gives the same results.
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Returns the names of the parents of the word, sorted by name.
+    pub fn parents(&self, name: &str) -> LingResult<Vec<String>> {
+        let word = try!(self.get_word(name).ok_or(LingError::NoSuchWord(name.to_owned())));
+        Ok(self.word_parents(&word))
+    }
+
+    /// Returns the names of the words that have the word as a parent, sorted by name.
+    pub fn children(&self, name: &str) -> LingResult<Vec<String>> {
+        let name = try!(self.real_name(name));
+        Ok(self.child_map().remove(&name).unwrap_or_default())
+    }
+
+    /// Returns the names of the ancestors of the word, the closest ones first.
+    ///
+    /// The ancestors at the same distance are sorted by name. A word in a cycle of parents is
+    /// not returned as it's own ancestor.
+    pub fn ancestors(&self, name: &str) -> LingResult<Vec<String>> {
+        let depths = try!(self.ancestor_depths(name));
+        let mut ancestors: Vec<(usize, String)> = depths.into_iter()
+            .filter(|&(_, depth)| depth > 0)
+            .map(|(name, depth)| (depth, name))
+            .collect();
+        ancestors.sort();
+        Ok(ancestors.into_iter().map(|(_, name)| name).collect())
+    }
+
+    /// Returns the names of the descendants of the word, the closest ones first.
+    ///
+    /// The descendants at the same distance are sorted by name. A word in a cycle of parents is
+    /// not returned as it's own descendant.
+    pub fn descendants(&self, name: &str) -> LingResult<Vec<String>> {
+        let name = try!(self.real_name(name));
+        let children = self.child_map();
+        let mut visited = HashSet::new();
+        visited.insert(name.clone());
+        let mut found = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(name);
+        while let Some(current) = queue.pop_front() {
+            for child in children.get(&current).into_iter().flat_map(|c| c) {
+                if visited.insert(child.clone()) {
+                    found.push(child.clone());
+                    queue.push_back(child.clone());
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Returns true if the word is the ancestor or one of it's descendants.
+    ///
+    /// Every word is itself, so `is_a(word, word)` is true for every stored word. Returns false
+    /// if either of the words isn't found.
+    pub fn is_a(&self, word: &str, ancestor: &str) -> bool {
+        match (self.ancestor_depths(word), self.real_name(ancestor)) {
+            (Ok(depths), Ok(ancestor)) => depths.contains_key(&ancestor),
+            _ => false,
+        }
+    }
+
+    /// Returns the lowest common ancestor of the two words, or `None` if they have no common
+    /// ancestors.
+    ///
+    /// A word counts as it's own ancestor, so the lowest common ancestor of a word and it's
+    /// parent is the parent. If many common ancestors aren't ancestors of each other, the one
+    /// closest to the words is returned, and the ties are broken by name.
+    pub fn lowest_common_ancestor(&self, a: &str, b: &str) -> LingResult<Option<String>> {
+        let depths_a = try!(self.ancestor_depths(a));
+        let depths_b = try!(self.ancestor_depths(b));
+        let common: Vec<&String> =
+            depths_a.keys().filter(|n| depths_b.contains_key(*n)).collect();
+        // a common ancestor of another common ancestor isn't the lowest one
+        let mut higher = HashSet::new();
+        for c in &common {
+            let depths = try!(self.ancestor_depths(c));
+            higher.extend(depths.into_iter().filter(|&(_, depth)| depth > 0).map(|(name, _)| name));
+        }
+        let lowest = common.into_iter()
+            .filter(|c| !higher.contains(*c))
+            .min_by_key(|&c| (depths_a[c] + depths_b[c], c))
+            .cloned();
+        Ok(lowest)
+    }
+
+    /// Returns the names of the stored words sorted so that every word comes after it's
+    /// parents.
+    ///
+    /// The words that could come at the same point are sorted by name.
+    /// Fails with `LingError::ParentCycle` if some words are their own ancestors.
+    pub fn topological_order(&self) -> LingResult<Vec<String>> {
+        let children = self.child_map();
+        let mut parent_counts: HashMap<String, usize> = self.iter_words()
+            .map(|w| (w.get_name().into_owned(), self.word_parents(&w).len()))
+            .collect();
+        let mut ready: BTreeSet<String> = parent_counts.iter()
+            .filter(|&(_, &count)| count == 0)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut order = Vec::with_capacity(parent_counts.len());
+        while let Some(name) = ready.iter().next().cloned() {
+            ready.remove(&name);
+            for child in children.get(&name).into_iter().flat_map(|c| c) {
+                let count = parent_counts.get_mut(child).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(child.clone());
+                }
+            }
+            order.push(name);
+        }
+
+        if order.len() < parent_counts.len() {
+            let cyclic = parent_counts.into_iter()
+                .filter(|&(_, count)| count > 0)
+                .map(|(name, _)| name)
+                .min()
+                .unwrap();
+            return Err(LingError::ParentCycle(cyclic));
+        }
+        Ok(order)
+    }
+
+    /// Returns the real name of the stored word with the given name or alias.
+    fn real_name(&self, name: &str) -> LingResult<String> {
+        self.resolve_name(name)
+            .map(ToOwned::to_owned)
+            .ok_or(LingError::NoSuchWord(name.to_owned()))
+    }
+
+    /// Returns the names of the stored parents of the word, sorted by name.
+    fn word_parents(&self, word: &GenericWord<U>) -> Vec<String> {
+        let parents: BTreeSet<String> = word.get_tags()
+            .iter()
+            .filter_map(|t| match **t {
+                LingTag::Parent(ref parent) => self.resolve_name(parent).map(ToOwned::to_owned),
+                _ => None,
+            })
+            .collect();
+        parents.into_iter().collect()
+    }
+
+    /// Returns a map from the names of the words into the names of their children, sorted by
+    /// name.
+    fn child_map(&self) -> HashMap<String, Vec<String>> {
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for word in self.iter_words() {
+            for parent in self.word_parents(&word) {
+                let name = word.get_name().into_owned();
+                children.entry(parent).or_insert_with(Vec::new).push(name);
+            }
+        }
+        for list in children.values_mut() {
+            list.sort();
+        }
+        children
+    }
+
+    /// Returns the ancestors of the word and the word itself with their distances to the word.
+    fn ancestor_depths(&self, name: &str) -> LingResult<HashMap<String, usize>> {
+        let name = try!(self.real_name(name));
+        let mut depths = HashMap::new();
+        depths.insert(name.clone(), 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(name);
+        while let Some(current) = queue.pop_front() {
+            let depth = depths[&current];
+            let word = self.get_word(&current as &str).unwrap();
+            for parent in self.word_parents(&word) {
+                if !depths.contains_key(&parent) {
+                    depths.insert(parent.clone(), depth + 1);
+                    queue.push_back(parent);
+                }
+            }
+        }
+        Ok(depths)
+    }
+}
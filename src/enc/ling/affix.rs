@@ -0,0 +1,272 @@
+//! Contains the affix rules used for inflecting and lemmatizing words.
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The key of the `LingTag::CustomSet` tag that lists the names of the affix classes a word
+/// can be inflected with.
+pub const AFFIX_KEY: &'static str = "affixes";
+
+/// Tells if the rules of an affix class change the start or the end of a word.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum AffixKind {
+    Prefix,
+    Suffix,
+}
+
+/// A part of an affix condition that matches a single character.
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum CondChar {
+    Any,
+    Char(char),
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl CondChar {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            CondChar::Any => true,
+            CondChar::Char(x) => x == c,
+            CondChar::OneOf(ref v) => v.contains(&c),
+            CondChar::NoneOf(ref v) => !v.contains(&c),
+        }
+    }
+}
+
+/// A rule that inflects a word by removing and adding characters at one end of it.
+///
+/// The rule applies to the words whose end matches the condition, like in Hunspell affix files.
+/// The condition is a list of characters, where `.` matches any character, `[abc]` matches one
+/// of the characters inside the brackets and `[^abc]` matches any other character.
+/// The condition of a suffix rule is matched against the end of the word and the condition of
+/// a prefix rule against the start of it, before removing the characters. The condition of a
+/// prefix combined with a suffix is matched against the word without the suffix.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AffixRule {
+    strip: String,
+    add: String,
+    condition: Vec<CondChar>,
+    /// The categories and values of the grammemes of the inflected forms.
+    grammemes: Vec<(String, String)>,
+}
+
+impl AffixRule {
+    /// Creates a rule that replaces `strip` with `add` in the words matching the condition.
+    ///
+    /// Fails with `LingError::InvalidAffixCondition` if the condition isn't valid.
+    pub fn new<T, V>(strip: T, add: V, condition: &str) -> LingResult<Self>
+        where T: Into<String>,
+              V: Into<String>
+    {
+        Ok(AffixRule {
+            strip: strip.into(),
+            add: add.into(),
+            condition: try!(parse_condition(condition)),
+            grammemes: Vec::new(),
+        })
+    }
+
+    /// Adds a grammeme to the forms this rule creates.
+    pub fn with_grammeme<T, V>(mut self, category: T, value: V) -> Self
+        where T: Into<String>,
+              V: Into<String>
+    {
+        self.grammemes.push((category.into(), value.into()));
+        self
+    }
+
+    pub fn get_strip(&self) -> &str {
+        &self.strip
+    }
+
+    pub fn get_add(&self) -> &str {
+        &self.add
+    }
+
+    /// Returns the categories and values of the grammemes of the forms this rule creates.
+    pub fn get_grammemes(&self) -> &[(String, String)] {
+        &self.grammemes
+    }
+
+    /// Returns true if the rule can be applied to the word.
+    pub fn matches(&self, kind: AffixKind, word: &str) -> bool {
+        self.can_strip(kind, word) && self.condition_matches(kind, word)
+    }
+
+    /// Returns true if the word has the characters the rule removes.
+    fn can_strip(&self, kind: AffixKind, word: &str) -> bool {
+        match kind {
+            AffixKind::Prefix => word.starts_with(&self.strip as &str),
+            AffixKind::Suffix => word.ends_with(&self.strip as &str),
+        }
+    }
+
+    /// Returns true if the end of the word matches the condition.
+    fn condition_matches(&self, kind: AffixKind, word: &str) -> bool {
+        let chars: Vec<char> = match kind {
+            AffixKind::Prefix => word.chars().collect(),
+            AffixKind::Suffix => word.chars().rev().collect(),
+        };
+        let condition: Vec<&CondChar> = match kind {
+            AffixKind::Prefix => self.condition.iter().collect(),
+            AffixKind::Suffix => self.condition.iter().rev().collect(),
+        };
+        chars.len() >= condition.len() &&
+        condition.iter().zip(chars).all(|(cond, c)| cond.matches(c))
+    }
+
+    /// Returns the form of the word created by this rule, or `None` if the rule can't be
+    /// applied to the word.
+    pub fn apply(&self, kind: AffixKind, word: &str) -> Option<String> {
+        self.apply_to_affixed(kind, word, word)
+    }
+
+    /// Returns the form created by this rule from a word that was created from the root by an
+    /// affix of the other kind, or `None` if the rule can't be applied.
+    ///
+    /// The condition is matched against the root like in Hunspell, so a prefix combined with a
+    /// suffix doesn't depend on the characters the suffix changed.
+    pub fn apply_to_affixed(&self, kind: AffixKind, word: &str, root: &str) -> Option<String> {
+        if !self.can_strip(kind, word) || !self.condition_matches(kind, root) {
+            return None;
+        }
+        Some(match kind {
+            AffixKind::Prefix => format!("{}{}", self.add, &word[self.strip.len()..]),
+            AffixKind::Suffix => format!("{}{}", &word[..word.len() - self.strip.len()], self.add),
+        })
+    }
+
+    /// Returns the word this rule creates the form from, or `None` if the form can't be created
+    /// with this rule.
+    pub fn unapply(&self, kind: AffixKind, form: &str) -> Option<String> {
+        self.unapply_affixed(kind, form).filter(|word| self.condition_matches(kind, word))
+    }
+
+    /// Returns the word this rule creates the form from when the word was created from a root
+    /// by an affix of the other kind, see `apply_to_affixed`.
+    ///
+    /// The condition isn't matched, since it's matched against the root.
+    pub fn unapply_affixed(&self, kind: AffixKind, form: &str) -> Option<String> {
+        let word = match kind {
+            AffixKind::Prefix if form.starts_with(&self.add as &str) => {
+                format!("{}{}", self.strip, &form[self.add.len()..])
+            }
+            AffixKind::Suffix if form.ends_with(&self.add as &str) => {
+                format!("{}{}", &form[..form.len() - self.add.len()], self.strip)
+            }
+            _ => return None,
+        };
+        if word.is_empty() { None } else { Some(word) }
+    }
+}
+
+/// A named set of affix rules of the same kind.
+///
+/// The words list the affix classes they can be inflected with in the `LingTag::CustomSet` tag
+/// with the key `AFFIX_KEY`, see `Encyclopedia::inflections`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AffixClass {
+    name: String,
+    kind: AffixKind,
+    cross_product: bool,
+    rules: Vec<AffixRule>,
+}
+
+impl AffixClass {
+    /// Creates an affix class without rules.
+    pub fn new<T: Into<String>>(name: T, kind: AffixKind) -> Self {
+        AffixClass {
+            name: name.into(),
+            kind: kind,
+            cross_product: false,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Allows combining the rules of this class with the rules of the other cross product
+    /// classes of the other kind, so a word can get both a prefix and a suffix.
+    pub fn cross_product(mut self, cross_product: bool) -> Self {
+        self.cross_product = cross_product;
+        self
+    }
+
+    /// Adds a rule to the class.
+    pub fn with_rule(mut self, rule: AffixRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Adds a rule to the class.
+    pub fn add_rule(&mut self, rule: AffixRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_kind(&self) -> AffixKind {
+        self.kind
+    }
+
+    pub fn is_cross_product(&self) -> bool {
+        self.cross_product
+    }
+
+    pub fn get_rules(&self) -> &[AffixRule] {
+        &self.rules
+    }
+
+    /// Returns the forms of the word created by the rules of this class with the rules that
+    /// created them.
+    pub fn apply(&self, word: &str) -> Vec<(String, &AffixRule)> {
+        self.rules.iter().filter_map(|r| r.apply(self.kind, word).map(|f| (f, r))).collect()
+    }
+
+    /// Returns the words the rules of this class can create the form from with the rules.
+    pub fn unapply(&self, form: &str) -> Vec<(String, &AffixRule)> {
+        self.rules.iter().filter_map(|r| r.unapply(self.kind, form).map(|w| (w, r))).collect()
+    }
+}
+
+/// Parses an affix condition, `.` alone matches every word.
+fn parse_condition(s: &str) -> LingResult<Vec<CondChar>> {
+    let err = || LingError::InvalidAffixCondition(s.to_owned());
+    if s == "." {
+        return Ok(Vec::new());
+    }
+    let mut condition = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        condition.push(match c {
+            '.' => CondChar::Any,
+            '[' => {
+                let mut set = Vec::new();
+                let mut negated = false;
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '^' if set.is_empty() && !negated => negated = true,
+                        ']' => {
+                            closed = true;
+                            break;
+                        }
+                        c => set.push(c),
+                    }
+                }
+                if !closed || set.is_empty() {
+                    return Err(err());
+                }
+                if negated {
+                    CondChar::NoneOf(set)
+                } else {
+                    CondChar::OneOf(set)
+                }
+            }
+            ']' => return Err(err()),
+            c => CondChar::Char(c),
+        });
+    }
+    Ok(condition)
+}
@@ -0,0 +1,158 @@
+//! Contains the collations used for sorting the display lists.
+use std::cmp::Ordering;
+
+/// The accented lowercase letters and the letters they are sorted as, unless a collation places
+/// them elsewhere.
+const BASE_LETTERS: &'static [(char, char)] = &[('à', 'a'), ('á', 'a'), ('â', 'a'), ('ã', 'a'),
+                                                ('ä', 'a'), ('å', 'a'), ('æ', 'a'), ('ç', 'c'),
+                                                ('č', 'c'), ('ð', 'd'), ('è', 'e'), ('é', 'e'),
+                                                ('ê', 'e'), ('ë', 'e'), ('ì', 'i'), ('í', 'i'),
+                                                ('î', 'i'), ('ï', 'i'), ('ñ', 'n'), ('ò', 'o'),
+                                                ('ó', 'o'), ('ô', 'o'), ('õ', 'o'), ('ö', 'o'),
+                                                ('ø', 'o'), ('š', 's'), ('ß', 's'), ('ù', 'u'),
+                                                ('ú', 'u'), ('û', 'u'), ('ü', 'u'), ('ý', 'y'),
+                                                ('ÿ', 'y'), ('ž', 'z')];
+
+/// A locale-aware order of texts.
+///
+/// The texts are compared letter by letter ignoring the case and the accents, so `apple`,
+/// `Banana` and `élan` are in that order. The accented letters are sorted as their base letters
+/// unless the collation places them elsewhere with `Collation::with_letter_after`, like the
+/// Finnish collation places `å`, `ä` and `ö` after `z`. The texts that differ only by the
+/// accents or the case are sorted by the accents and then with the lowercase letters first.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Collation {
+    /// The letters that are sorted after other letters, with the letters they are sorted after.
+    tailoring: Vec<(char, char)>,
+}
+
+impl Collation {
+    /// Creates a collation that sorts every accented letter as it's base letter.
+    pub fn new() -> Self {
+        Collation { tailoring: Vec::new() }
+    }
+
+    /// Returns the collation of the locale, like `fi` or `sv-SE`.
+    ///
+    /// The collations of Finnish, Swedish, Danish and Norwegian are known, and the other locales
+    /// use the collation created by `Collation::new`.
+    pub fn for_locale(locale: &str) -> Self {
+        let language = locale.split(&['-', '_'][..]).next().unwrap_or("");
+        let collation = Collation::new();
+        match &language.to_lowercase() as &str {
+            "fi" | "sv" => {
+                collation.with_letter_after('å', 'z')
+                    .with_letter_after('ä', 'z')
+                    .with_letter_after('ö', 'z')
+            }
+            "da" | "nb" | "nn" | "no" => {
+                collation.with_letter_after('æ', 'z')
+                    .with_letter_after('ø', 'z')
+                    .with_letter_after('å', 'z')
+            }
+            _ => collation,
+        }
+    }
+
+    /// Sorts the letter after the base letter of the other letter and the letters already
+    /// sorted after it.
+    ///
+    /// The case of the letters doesn't matter.
+    pub fn with_letter_after(mut self, letter: char, after: char) -> Self {
+        let letter = lowercase(letter);
+        self.tailoring.retain(|&(l, _)| l != letter);
+        self.tailoring.push((letter, lowercase(after)));
+        self
+    }
+
+    /// Compares the texts in this collation.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.sort_key(a).cmp(&self.sort_key(b))
+    }
+
+    /// Returns the key the text is sorted by in this collation.
+    ///
+    /// Comparing the keys is the same as comparing the texts with `compare`, so sorting a long
+    /// list by the keys computes the weights of every text only once.
+    pub fn sort_key(&self, s: &str) -> SortKey {
+        SortKey {
+            primary: s.chars().map(|c| self.weight(c)).collect(),
+            lower: s.chars().map(lowercase).collect(),
+            // the lowercase letters first
+            upper: s.chars().map(char::is_uppercase).collect(),
+            text: s.to_owned(),
+        }
+    }
+
+    /// Returns the weight of the letter, which is the base letter it is sorted after and it's
+    /// position among the letters sorted after the base letter.
+    fn weight(&self, c: char) -> (u32, usize) {
+        let c = lowercase(c);
+        match self.tailoring.iter().position(|&(l, _)| l == c) {
+            Some(i) => {
+                let after = self.tailoring[i].1;
+                let position = self.tailoring[..i].iter().filter(|&&(_, a)| a == after).count();
+                (base_letter(after) as u32, position + 1)
+            }
+            None => (base_letter(c) as u32, 0),
+        }
+    }
+}
+
+/// The key a text is sorted by, see `Collation::sort_key` and `SortOrder::sort_key`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Default)]
+pub struct SortKey {
+    /// The weights of the letters.
+    primary: Vec<(u32, usize)>,
+    /// The lowercase letters, which tell the accents apart.
+    lower: Vec<char>,
+    /// The case of the letters.
+    upper: Vec<bool>,
+    text: String,
+}
+
+/// The order of the items of a list.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SortOrder {
+    /// The order the items were added in.
+    Insertion,
+    /// The order of the Unicode code points of the names, which is case-sensitive.
+    CodePoint,
+    /// The order of the names in the collation.
+    Collated(Collation),
+}
+
+impl SortOrder {
+    /// Compares the names in this order.
+    ///
+    /// All names are equal in the insertion order, so a stable sort of a list in the insertion
+    /// order keeps that order.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match *self {
+            SortOrder::Insertion => Ordering::Equal,
+            SortOrder::CodePoint => a.cmp(b),
+            SortOrder::Collated(ref collation) => collation.compare(a, b),
+        }
+    }
+
+    /// Returns the key the name is sorted by in this order.
+    ///
+    /// The keys are compared like the names with `compare`, so they can be used with
+    /// `sort_by_cached_key` to sort long lists without comparing the names again and again.
+    pub fn sort_key(&self, name: &str) -> SortKey {
+        match *self {
+            SortOrder::Insertion => SortKey::default(),
+            SortOrder::CodePoint => SortKey { text: name.to_owned(), ..SortKey::default() },
+            SortOrder::Collated(ref collation) => collation.sort_key(name),
+        }
+    }
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Returns the letter the lowercase letter is sorted as when it isn't tailored.
+fn base_letter(c: char) -> char {
+    BASE_LETTERS.iter().find(|&&(l, _)| l == c).map_or(c, |&(_, base)| base)
+}
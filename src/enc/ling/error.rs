@@ -1,14 +1,41 @@
 use std::fmt;
+use std::io;
 use std::error::Error;
 
 /// An error created by an operation of an `EncLing` `Encyclopedia`.
 #[derive(Debug)]
 pub enum LingError {
     FmtError(fmt::Error),
+    IoError(io::Error),
     InvalidTags, // TODO
+    TransactionAborted,
     NoSuchGrammCat(String),
     NoSuchGrammeme(String),
     NoDefaultOrValue(String),
+    NoSuchWord(String),
+    WordExists(String),
+    NoSuchTagGroup(String),
+    TagGroupExists(String),
+    /// The tag group with the given name is used by a word or another tag group.
+    TagGroupInUse(String),
+    /// The value of the tag with the given key isn't of the expected type.
+    WrongValueType(String, &'static str),
+    /// A custom tag uses a key that isn't declared in the schema.
+    UnknownTagKey(String),
+    /// A word doesn't have a key the schema requires for it's type.
+    MissingTagKey(String),
+    /// The word with the given name failed a check for the given reason.
+    InvalidWord(String, Box<LingError>),
+    /// The word with the given name is it's own ancestor.
+    ParentCycle(String),
+    /// A `LingTag::Relation` uses a relation type that isn't declared.
+    UnknownRelation(String),
+    /// An affix rule has a condition that can't be parsed.
+    InvalidAffixCondition(String),
+    /// The word with the given name has no form with the requested grammemes.
+    NoSuchForm(String),
+    /// A syntax error in an encyclopedia source file at the given line.
+    ParseError(usize, String),
 }
 
 impl fmt::Display for LingError {
@@ -18,9 +45,26 @@ impl fmt::Display for LingError {
         // add possible additional information
         match self {
             &LingError::FmtError(ref e) => write!(f, ": {}", e),
+            &LingError::IoError(ref e) => write!(f, ": {}", e),
             &LingError::NoSuchGrammCat(ref s) => write!(f, ": {}", s),
             &LingError::NoSuchGrammeme(ref s) => write!(f, ": {}", s),
             &LingError::NoDefaultOrValue(ref s) => write!(f, ": {}", s),
+            &LingError::NoSuchWord(ref s) => write!(f, ": {}", s),
+            &LingError::WordExists(ref s) => write!(f, ": {}", s),
+            &LingError::NoSuchTagGroup(ref s) => write!(f, ": {}", s),
+            &LingError::TagGroupExists(ref s) => write!(f, ": {}", s),
+            &LingError::TagGroupInUse(ref s) => write!(f, ": {}", s),
+            &LingError::WrongValueType(ref key, expected) => {
+                write!(f, ": {} (expected {})", key, expected)
+            }
+            &LingError::UnknownTagKey(ref s) => write!(f, ": {}", s),
+            &LingError::MissingTagKey(ref s) => write!(f, ": {}", s),
+            &LingError::InvalidWord(ref s, ref e) => write!(f, " `{}`: {}", s, e),
+            &LingError::ParentCycle(ref s) => write!(f, ": {}", s),
+            &LingError::UnknownRelation(ref s) => write!(f, ": {}", s),
+            &LingError::InvalidAffixCondition(ref s) => write!(f, ": {}", s),
+            &LingError::NoSuchForm(ref s) => write!(f, ": {}", s),
+            &LingError::ParseError(line, ref s) => write!(f, " at line {}: {}", line, s),
             _ => Ok(()),
         }
     }
@@ -30,12 +74,34 @@ impl Error for LingError {
     fn description(&self) -> &str {
         match self {
             &LingError::FmtError(_) => "format error",
+            &LingError::IoError(_) => "io error",
             &LingError::InvalidTags => "invalid word tags",
+            &LingError::TransactionAborted => "the transaction has been aborted",
             &LingError::NoSuchGrammCat(_) => "grammatical category not found",
             &LingError::NoSuchGrammeme(_) => "grammeme value not found",
             &LingError::NoDefaultOrValue(_) => {
                 "no grammeme value was given and there is no default value for the category"
             }
+            &LingError::NoSuchWord(_) => "word not found",
+            &LingError::WordExists(_) => "a word with the same name already exists",
+            &LingError::NoSuchTagGroup(_) => "tag group not found",
+            &LingError::TagGroupExists(_) => "a tag group with the same name already exists",
+            &LingError::TagGroupInUse(_) => "the tag group is in use",
+            &LingError::WrongValueType(..) => "the tag value has a wrong type",
+            &LingError::UnknownTagKey(_) => "the tag key isn't declared in the schema",
+            &LingError::MissingTagKey(_) => "a required tag key is missing",
+            &LingError::InvalidWord(..) => "invalid word",
+            &LingError::ParentCycle(_) => "the word is it's own ancestor",
+            &LingError::UnknownRelation(_) => "the relation type is not declared",
+            &LingError::InvalidAffixCondition(_) => "invalid affix condition",
+            &LingError::NoSuchForm(_) => "word form not found",
+            &LingError::ParseError(..) => "syntax error",
         }
     }
 }
+
+impl From<io::Error> for LingError {
+    fn from(e: io::Error) -> Self {
+        LingError::IoError(e)
+    }
+}
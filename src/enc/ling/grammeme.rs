@@ -72,6 +72,7 @@ impl<'a> GrammCategory<'a> {
 /// A valid grammeme that also contains the information about the category it is in.
 ///
 /// Does not contain a reference to the actual `GrammCategory` struct, just to it's name.
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Grammeme<'a> {
     cat_name: Cow<'a, str>,
     value: Cow<'a, str>,
@@ -1,11 +1,15 @@
 //! Contains the encyclopedias lexicographical features.
 
 mod tag;
+mod value;
 pub mod grammeme;
+pub mod affix;
+pub mod collation;
 pub mod error;
 
 #[cfg(test)]
 mod test;
 
 pub use self::tag::*;
+pub use self::value::*;
 pub use self::error::*;
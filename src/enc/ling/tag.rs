@@ -1,34 +1,133 @@
 //! Contains the LingTag struct that is used to store a word's information.
 use std::borrow::Cow;
 use std::collections::BTreeSet;
+use std::fmt::Debug;
+
+use util::EncSysType;
+use enc::ling::value::TagValue;
+
+/// A marker trait for the user-defined tag types that can be stored in `LingTag::User`.
+pub trait UserTag: EncSysType + Debug + 'static {}
+impl<T: EncSysType + Debug + 'static> UserTag for T {}
+
+/// The user tag type of the words that don't have user-defined tags.
+///
+/// This has no values, so no `LingTag::User` tags can be created with it.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum NoUserTag {}
 
 /// A word tag with encyclopedic and lexicographical information.
 ///
 /// Tries to take as little assumptions about the used language as possible.
+/// The user can define their own tags with the type `U`, which is stored in the `User` variant.
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
-pub enum LingTag {
+pub enum LingTag<U = NoUserTag> {
     Type(String),
     UseTagGroup(String),
     Parent(String),
     HasWords(Vec<String>),
     Synonym(String),
+    /// A typed relation to another word, like `Relation("antonym", "cold")`.
+    /// See `Encyclopedia::declare_relation` and `Encyclopedia::related`.
+    Relation(String, String),
     // GrammemeRules(),
     Custom(String),
     CustomStr(String, Option<String>),
     CustomVec(String, Vec<String>),
     CustomSet(String, BTreeSet<String>),
+    /// A custom key with a typed value.
+    Value(String, TagValue),
+    /// Removes the given tag when this word is applied as a modifier to another word or when
+    /// the tag is inherited from a parent or a tag group, see `LingTag::overrides`.
+    Remove(Box<LingTag<U>>),
+    /// A user-defined tag.
+    User(U),
 }
 
-impl<'a> From<&'a LingTag> for Cow<'a, LingTag> {
-    fn from(t: &'a LingTag) -> Self {
+impl<'a, U: UserTag> From<&'a LingTag<U>> for Cow<'a, LingTag<U>> {
+    fn from(t: &'a LingTag<U>) -> Self {
         Cow::Borrowed(t)
     }
 }
 
-impl<'a> From<LingTag> for Cow<'a, LingTag> {
-    fn from(t: LingTag) -> Self {
+impl<'a, U: UserTag> From<LingTag<U>> for Cow<'a, LingTag<U>> {
+    fn from(t: LingTag<U>) -> Self {
         Cow::Owned(t)
     }
 }
 
-impl LingTag {}
+impl<U> LingTag<U> {
+    /// Returns the key of a custom tag or a value tag, or `None` for the other tags.
+    pub fn key(&self) -> Option<&str> {
+        match *self {
+            LingTag::Custom(ref k) |
+            LingTag::CustomStr(ref k, _) |
+            LingTag::CustomVec(ref k, _) |
+            LingTag::CustomSet(ref k, _) |
+            LingTag::Value(ref k, _) => Some(k),
+            _ => None,
+        }
+    }
+}
+
+impl LingTag<NoUserTag> {
+    /// Returns this tag as a tag of the words with the user-defined tags of the type `U`.
+    ///
+    /// This is how the tags read from the formats without user-defined tags are added to any
+    /// encyclopedia.
+    pub fn with_user_tag<U>(self) -> LingTag<U> {
+        match self {
+            LingTag::Type(s) => LingTag::Type(s),
+            LingTag::UseTagGroup(s) => LingTag::UseTagGroup(s),
+            LingTag::Parent(s) => LingTag::Parent(s),
+            LingTag::HasWords(v) => LingTag::HasWords(v),
+            LingTag::Synonym(s) => LingTag::Synonym(s),
+            LingTag::Relation(r, s) => LingTag::Relation(r, s),
+            LingTag::Custom(k) => LingTag::Custom(k),
+            LingTag::CustomStr(k, v) => LingTag::CustomStr(k, v),
+            LingTag::CustomVec(k, v) => LingTag::CustomVec(k, v),
+            LingTag::CustomSet(k, v) => LingTag::CustomSet(k, v),
+            LingTag::Value(k, v) => LingTag::Value(k, v),
+            LingTag::Remove(t) => LingTag::Remove(Box::new(t.with_user_tag())),
+            LingTag::User(u) => match u {},
+        }
+    }
+}
+
+impl<U: Clone> LingTag<U> {
+    /// Returns this tag without the type of the user-defined tags, or `None` if it is a
+    /// `LingTag::User` tag or removes one.
+    pub fn without_user_tag(&self) -> Option<LingTag> {
+        Some(match *self {
+            LingTag::Type(ref s) => LingTag::Type(s.clone()),
+            LingTag::UseTagGroup(ref s) => LingTag::UseTagGroup(s.clone()),
+            LingTag::Parent(ref s) => LingTag::Parent(s.clone()),
+            LingTag::HasWords(ref v) => LingTag::HasWords(v.clone()),
+            LingTag::Synonym(ref s) => LingTag::Synonym(s.clone()),
+            LingTag::Relation(ref r, ref s) => LingTag::Relation(r.clone(), s.clone()),
+            LingTag::Custom(ref k) => LingTag::Custom(k.clone()),
+            LingTag::CustomStr(ref k, ref v) => LingTag::CustomStr(k.clone(), v.clone()),
+            LingTag::CustomVec(ref k, ref v) => LingTag::CustomVec(k.clone(), v.clone()),
+            LingTag::CustomSet(ref k, ref v) => LingTag::CustomSet(k.clone(), v.clone()),
+            LingTag::Value(ref k, ref v) => LingTag::Value(k.clone(), v.clone()),
+            LingTag::Remove(ref t) => LingTag::Remove(Box::new(match t.without_user_tag() {
+                Some(t) => t,
+                None => return None,
+            })),
+            LingTag::User(_) => return None,
+        })
+    }
+}
+
+impl<U: PartialEq> LingTag<U> {
+    /// Returns true if adding or removing this tag replaces or removes the other tag.
+    ///
+    /// This is the case if the tags are equal or if they are custom or value tags with the same
+    /// key, so for example `Remove(CustomStr("hp", None))` removes any `hp` value.
+    pub fn overrides(&self, other: &LingTag<U>) -> bool {
+        match self.key() {
+            Some(key) => other.key() == Some(key),
+            None => self == other,
+        }
+    }
+}
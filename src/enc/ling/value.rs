@@ -0,0 +1,237 @@
+//! Contains the `TagValue` enum for the typed values of the tags.
+use std::{f64, fmt};
+use std::hash::{Hash, Hasher};
+
+/// A typed value of a `LingTag::Value` tag.
+///
+/// The floats are compared and hashed by their bit patterns, so that the values can be stored in
+/// sets. This means that `NaN` equals itself, but `0.0` and `-0.0` are different values.
+#[derive(Clone, Debug)]
+pub enum TagValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// An inclusive range of integers.
+    IntRange(i64, i64),
+    /// An inclusive range of floats.
+    FloatRange(f64, f64),
+    Str(String),
+    List(Vec<TagValue>),
+}
+
+impl TagValue {
+    /// Parses an unquoted value.
+    ///
+    /// `true` and `false` are booleans, `1` is an integer, `1.5` is a float and `1..5` or
+    /// `1.5..2` are ranges. The start of a range can't be greater than it's end. The special
+    /// floats are written as `nan`, `inf` and `-inf`. Anything else is an error, so strings have
+    /// to be created with `TagValue::Str` or written in quotes in the source files.
+    pub fn parse(s: &str) -> Result<TagValue, String> {
+        match s {
+            "true" => return Ok(TagValue::Bool(true)),
+            "false" => return Ok(TagValue::Bool(false)),
+            _ => (),
+        }
+        if let Some(i) = s.find("..") {
+            let (start, end) = (&s[..i], &s[i + 2..]);
+            let range = if let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse()) {
+                if start <= end {
+                    Some(TagValue::IntRange(start, end))
+                } else {
+                    None
+                }
+            } else if let (Some(start), Some(end)) = (parse_float(start), parse_float(end)) {
+                if start > end {
+                    None
+                } else {
+                    Some(TagValue::FloatRange(start, end))
+                }
+            } else {
+                None
+            };
+            return range.ok_or_else(|| format!("invalid range `{}`", s));
+        }
+        if let Ok(i) = s.parse() {
+            return Ok(TagValue::Int(i));
+        }
+        if let Some(f) = parse_float(s) {
+            return Ok(TagValue::Float(f));
+        }
+        Err(format!("invalid value `{}`, strings have to be quoted", s))
+    }
+
+    /// Returns the name of the type of the value.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            TagValue::Int(_) => "int",
+            TagValue::Float(_) => "float",
+            TagValue::Bool(_) => "bool",
+            TagValue::IntRange(..) => "int range",
+            TagValue::FloatRange(..) => "float range",
+            TagValue::Str(_) => "string",
+            TagValue::List(_) => "list",
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            TagValue::Int(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float. Integers are converted into floats.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            TagValue::Float(f) => Some(f),
+            TagValue::Int(i) => Some(i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            TagValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_range(&self) -> Option<(i64, i64)> {
+        match *self {
+            TagValue::IntRange(start, end) => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float range. Integer ranges are converted into float ranges.
+    pub fn as_float_range(&self) -> Option<(f64, f64)> {
+        match *self {
+            TagValue::FloatRange(start, end) => Some((start, end)),
+            TagValue::IntRange(start, end) => Some((start as f64, end as f64)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            TagValue::Str(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[TagValue]> {
+        match *self {
+            TagValue::List(ref v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for TagValue {
+    fn eq(&self, other: &TagValue) -> bool {
+        use self::TagValue::*;
+        match (self, other) {
+            (&Int(a), &Int(b)) => a == b,
+            (&Float(a), &Float(b)) => a.to_bits() == b.to_bits(),
+            (&Bool(a), &Bool(b)) => a == b,
+            (&IntRange(a1, a2), &IntRange(b1, b2)) => a1 == b1 && a2 == b2,
+            (&FloatRange(a1, a2), &FloatRange(b1, b2)) => {
+                a1.to_bits() == b1.to_bits() && a2.to_bits() == b2.to_bits()
+            }
+            (&Str(ref a), &Str(ref b)) => a == b,
+            (&List(ref a), &List(ref b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TagValue {}
+
+impl Hash for TagValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use self::TagValue::*;
+        self.type_name().hash(state);
+        match *self {
+            Int(i) => i.hash(state),
+            Float(f) => f.to_bits().hash(state),
+            Bool(b) => b.hash(state),
+            IntRange(start, end) => (start, end).hash(state),
+            FloatRange(start, end) => (start.to_bits(), end.to_bits()).hash(state),
+            Str(ref s) => s.hash(state),
+            List(ref v) => v.hash(state),
+        }
+    }
+}
+
+/// Parses a float in the plain decimal notation or one of `nan`, `inf` and `-inf`.
+fn parse_float(s: &str) -> Option<f64> {
+    match s {
+        "nan" => Some(f64::NAN),
+        "inf" => Some(f64::INFINITY),
+        "-inf" => Some(f64::NEG_INFINITY),
+        // accept only the plain decimal notation, so words like `infinity` need quotes
+        _ if s.starts_with(|c: char| c.is_digit(10) || c == '-' || c == '+' || c == '.') => {
+            s.parse().ok().filter(|f: &f64| !f.is_nan() && !f.is_infinite())
+        }
+        _ => None,
+    }
+}
+
+/// Writes a float in the form `parse_float` reads it.
+fn write_float(f: &mut fmt::Formatter, x: f64) -> Result<(), fmt::Error> {
+    if x.is_nan() {
+        f.write_str("nan")
+    } else {
+        write!(f, "{:?}", x)
+    }
+}
+
+/// Writes the value in the same form `TagValue::parse` reads it.
+///
+/// Strings are written in quotes with `\"`, `\\`, `\n` and `\r` as escapes. Every NaN is
+/// written as `nan`, so the payload and the sign of a NaN are lost.
+///
+/// The elements of lists are separated by spaces and the nested lists are written in brackets
+/// separated from the elements by spaces, like `1 [ 2 3 ] 4`. `TagValue::parse` doesn't read
+/// lists, but the `ValueList` lines of the source format do.
+impl fmt::Display for TagValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TagValue::Int(i) => write!(f, "{}", i),
+            TagValue::Float(x) => write_float(f, x),
+            TagValue::Bool(b) => write!(f, "{}", b),
+            TagValue::IntRange(start, end) => write!(f, "{}..{}", start, end),
+            TagValue::FloatRange(start, end) => {
+                try!(write_float(f, start));
+                try!(f.write_str(".."));
+                write_float(f, end)
+            }
+            TagValue::Str(ref s) => {
+                try!(f.write_str("\""));
+                for c in s.chars() {
+                    match c {
+                        '"' => try!(f.write_str("\\\"")),
+                        '\\' => try!(f.write_str("\\\\")),
+                        '\n' => try!(f.write_str("\\n")),
+                        '\r' => try!(f.write_str("\\r")),
+                        c => try!(write!(f, "{}", c)),
+                    }
+                }
+                f.write_str("\"")
+            }
+            TagValue::List(ref v) => {
+                for (i, value) in v.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(" "));
+                    }
+                    match *value {
+                        TagValue::List(ref inner) if inner.is_empty() => try!(f.write_str("[ ]")),
+                        TagValue::List(_) => try!(write!(f, "[ {} ]", value)),
+                        _ => try!(write!(f, "{}", value)),
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
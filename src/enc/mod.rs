@@ -1,13 +1,34 @@
 //! Contains the encyclopedia features.
 mod encyclopedia;
 mod word;
+mod change;
+mod transaction;
+mod store;
+mod schema;
+mod resolve;
+mod provenance;
+mod graph;
+mod relation;
+mod morph;
+mod coverage;
+mod render;
 pub mod ling;
+pub mod format;
 
 #[cfg(test)]
 mod test;
 
 pub use self::encyclopedia::*;
 pub use self::word::*;
+pub use self::change::*;
+pub use self::transaction::*;
+pub use self::store::WordId;
+pub use self::schema::*;
+pub use self::resolve::*;
+pub use self::provenance::*;
+pub use self::morph::*;
+pub use self::coverage::*;
+pub use self::render::*;
 
 // / A type used as the word name.
 // pub type WordName = &str;
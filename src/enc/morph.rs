@@ -0,0 +1,162 @@
+//! Contains the inflection and lemmatization of words with affix classes.
+use std::borrow::Cow;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::GenericWord;
+use enc::ling::UserTag;
+use enc::ling::affix::{AFFIX_KEY, AffixClass, AffixKind, AffixRule};
+use enc::ling::grammeme::Grammeme;
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// A form of a word with the grammemes it expresses.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct WordForm {
+    /// The name of the word the form is created from.
+    pub lemma: String,
+    pub form: String,
+    pub grammemes: Vec<Grammeme<'static>>,
+}
+
+/// The morphology of the words.
+///
+/// A word is inflected with the affix classes it lists in the `LingTag::CustomSet` tag with the
+/// key `AFFIX_KEY`, see `Encyclopedia::add_affix_class`. The affix classes that aren't stored
+/// are ignored. A prefix and a suffix are combined if both of their classes are cross product
+/// classes, see `AffixClass::cross_product`.
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Returns the inflected forms of the word with the given name.
+    ///
+    /// The word itself isn't included. The forms are in the order of the names of the affix
+    /// classes and the order of their rules.
+    pub fn inflections(&self, name: &str) -> LingResult<Vec<WordForm>> {
+        let word = try!(self.get_word(name).ok_or(LingError::NoSuchWord(name.to_owned())));
+        let lemma = word.get_name().into_owned();
+        let classes = self.word_affix_classes(&word);
+
+        let mut forms = Vec::new();
+        for class in &classes {
+            for (form, rule) in class.apply(&lemma) {
+                if class.get_kind() == AffixKind::Suffix && class.is_cross_product() {
+                    let prefixes = classes.iter().filter(|c| {
+                        c.get_kind() == AffixKind::Prefix && c.is_cross_product()
+                    });
+                    // the conditions of the prefixes are matched against the lemma
+                    for prefix in prefixes {
+                        for prefix_rule in prefix.get_rules() {
+                            if let Some(prefixed) =
+                                prefix_rule.apply_to_affixed(AffixKind::Prefix, &form, &lemma) {
+                                forms.push(word_form(&lemma, prefixed, &[rule, prefix_rule]));
+                            }
+                        }
+                    }
+                }
+                forms.push(word_form(&lemma, form, &[rule]));
+            }
+        }
+        Ok(forms)
+    }
+
+    /// Returns the forms of the word with the given name that express all of the given
+    /// grammemes.
+    ///
+    /// The word itself is returned if no grammemes are given.
+    pub fn inflect(&self, name: &str, grammemes: &[Grammeme]) -> LingResult<Vec<String>> {
+        let mut forms = Vec::new();
+        let lemma = try!(self.resolve_name(name).ok_or(LingError::NoSuchWord(name.to_owned())));
+        if grammemes.is_empty() {
+            forms.push(lemma.to_owned());
+        }
+        for form in try!(self.inflections(name)) {
+            let matches = grammemes.iter().all(|g| {
+                form.grammemes.iter().any(|f| {
+                    f.get_category_name() == g.get_category_name() &&
+                    f.get_value() == g.get_value()
+                })
+            });
+            if matches && !forms.contains(&form.form) {
+                forms.push(form.form);
+            }
+        }
+        Ok(forms)
+    }
+
+    /// Returns the words the form can be created from with the grammemes the form expresses.
+    ///
+    /// If a word with the name of the form is stored, it is included without grammemes.
+    /// The other results are in the order of the names of the affix classes.
+    pub fn lemmatize(&self, form: &str) -> Vec<WordForm> {
+        let mut results = Vec::new();
+        if let Some(lemma) = self.resolve_name(form) {
+            results.push(word_form(lemma, form.to_owned(), &[]));
+        }
+
+        let mut classes: Vec<&AffixClass> = self.iter_affix_classes().collect();
+        classes.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        for class in &classes {
+            for (stem, rule) in class.unapply(form) {
+                if self.has_affix_class(&stem, class) {
+                    let lemma = self.resolve_name(&stem).unwrap().to_owned();
+                    results.push(word_form(&lemma, form.to_owned(), &[rule]));
+                }
+            }
+            if class.get_kind() != AffixKind::Prefix || !class.is_cross_product() {
+                continue;
+            }
+            for rule in class.get_rules() {
+                let stem = match rule.unapply_affixed(AffixKind::Prefix, form) {
+                    Some(stem) => stem,
+                    None => continue,
+                };
+                let suffixes = classes.iter().filter(|c| {
+                    c.get_kind() == AffixKind::Suffix && c.is_cross_product()
+                });
+                for suffix in suffixes {
+                    for (lemma, suffix_rule) in suffix.unapply(&stem) {
+                        // the condition of the prefix is matched against the lemma
+                        if rule.apply_to_affixed(AffixKind::Prefix, &stem, &lemma).is_some() &&
+                           self.has_affix_class(&lemma, class) &&
+                           self.has_affix_class(&lemma, suffix) {
+                            let lemma = self.resolve_name(&lemma).unwrap().to_owned();
+                            let rules = [suffix_rule, rule];
+                            results.push(word_form(&lemma, form.to_owned(), &rules));
+                        }
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    /// Returns the stored affix classes of the word, sorted by name.
+    fn word_affix_classes(&self, word: &GenericWord<U>) -> Vec<&AffixClass> {
+        let mut names = word.get_custom_values(AFFIX_KEY).unwrap_or_default();
+        names.sort();
+        names.into_iter().filter_map(|n| self.get_affix_class(n)).collect()
+    }
+
+    /// Returns true if the word with the given name is stored and can be inflected with the
+    /// affix class.
+    fn has_affix_class(&self, name: &str, class: &AffixClass) -> bool {
+        match self.get_word(name) {
+            Some(word) => {
+                word.get_custom_values(AFFIX_KEY)
+                    .map_or(false, |v| v.contains(&class.get_name()))
+            }
+            None => false,
+        }
+    }
+}
+
+/// Creates a word form with the grammemes of the rules that created it.
+fn word_form(lemma: &str, form: String, rules: &[&AffixRule]) -> WordForm {
+    WordForm {
+        lemma: lemma.to_owned(),
+        form: form,
+        grammemes: rules.iter()
+            .flat_map(|r| r.get_grammemes())
+            .map(|&(ref c, ref v)| Grammeme::new(Cow::Owned(c.clone()), Cow::Owned(v.clone())))
+            .collect(),
+    }
+}
@@ -0,0 +1,16 @@
+//! Contains the `Provenance` struct telling where a tag of a word was defined.
+use std::path::PathBuf;
+
+/// Tells where a tag of a stored word was defined.
+///
+/// Recorded by an `Encyclopedia` when provenance tracking is enabled, see
+/// `Encyclopedia::enable_provenance`.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Provenance {
+    /// The source file the tag was loaded from.
+    pub file: Option<PathBuf>,
+    /// The line of the source file the tag was defined at.
+    pub line: Option<usize>,
+    /// The layer of content, like a mod, that was active when the tag was added.
+    pub layer: Option<String>,
+}
@@ -0,0 +1,98 @@
+//! Contains the queries to the typed relations between words.
+use std::collections::BTreeSet;
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The lexical relations of the words.
+///
+/// A word is related to another word by the `LingTag::Relation` tags it has. The relations that
+/// have a declared inverse also go the other way, so if `wheel` has `Relation("part_of", "car")`
+/// and `part_of` is the inverse of `has_part`, `car` is related to `wheel` with `has_part`
+/// without having a tag for it, see `Encyclopedia::declare_inverse_relations`.
+///
+/// Only the own tags of the words are used, the relations aren't inherited from the parents.
+/// The returned lists are sorted by the relation types and the names.
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Returns the names of the words related to the word with the given relation type.
+    pub fn related(&self, name: &str, relation: &str) -> LingResult<Vec<String>> {
+        let all = try!(self.relations_of(name));
+        Ok(all.into_iter().filter(|&(ref r, _)| r == relation).map(|(_, w)| w).collect())
+    }
+
+    /// Returns every relation type and related word of the word, including the ones inferred
+    /// from the inverse relations.
+    ///
+    /// Only the tags of the word and of the words that have relations to it are read.
+    pub fn relations_of(&self, name: &str) -> LingResult<Vec<(String, String)>> {
+        let id = try!(self.word_id(name).ok_or(LingError::NoSuchWord(name.to_owned())));
+        let word = self.get_word_by_id(id).unwrap();
+        let name = word.get_name();
+        let mut relations = BTreeSet::new();
+        for tag in word.get_tags() {
+            if let LingTag::Relation(ref relation, ref other) = **tag {
+                let other = self.resolve_name(other).unwrap_or(other);
+                relations.insert((relation.clone(), other.to_owned()));
+            }
+        }
+        for source in self.relation_sources(id) {
+            let source = match self.get_word_by_id(source) {
+                Some(source) => source,
+                None => continue,
+            };
+            for tag in source.get_tags() {
+                if let LingTag::Relation(ref relation, ref other) = **tag {
+                    let inverse = self.get_inverse_relation(relation);
+                    if let (Some(inverse), Some(other)) = (inverse, self.resolve_name(other)) {
+                        if other == name {
+                            relations.insert((inverse.to_owned(), source.get_name().into_owned()));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(relations.into_iter().collect())
+    }
+
+    /// Returns true if the word is related to the other word with the given relation type.
+    pub fn has_relation(&self, name: &str, relation: &str, other: &str) -> bool {
+        let other = self.resolve_name(other).unwrap_or(other);
+        match self.related(name, relation) {
+            Ok(related) => related.iter().any(|w| w == other),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns every pair of related words with the given relation type.
+    pub fn relation_pairs(&self, relation: &str) -> Vec<(String, String)> {
+        self.relation_triples()
+            .into_iter()
+            .filter(|&(_, ref r, _)| r == relation)
+            .map(|(w, _, other)| (w, other))
+            .collect()
+    }
+
+    /// Returns every relation as a word, a relation type and a related word, with the inverses
+    /// of the relations added.
+    ///
+    /// This reads every word, so the queries about a single word use `relations_of` instead.
+    fn relation_triples(&self) -> BTreeSet<(String, String, String)> {
+        let mut triples = BTreeSet::new();
+        for word in self.iter_words() {
+            let name = word.get_name();
+            for tag in word.get_tags() {
+                if let LingTag::Relation(ref relation, ref other) = **tag {
+                    let other = self.resolve_name(other).unwrap_or(other).to_owned();
+                    if let Some(inverse) = self.get_inverse_relation(relation) {
+                        triples.insert((other.clone(), inverse.to_owned(), name.to_string()));
+                    }
+                    triples.insert((name.to_string(), relation.clone(), other));
+                }
+            }
+        }
+        triples
+    }
+}
@@ -0,0 +1,87 @@
+//! Contains the rendering of the word names and forms into texts.
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::coverage::translates_form;
+use enc::ling::{LingTag, UserTag};
+use enc::ling::grammeme::Grammeme;
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The accented versions of the lowercase ASCII letters used by `pseudo_localize`.
+const ACCENTED_LOWER: &'static str = "åƀçðéƒĝĥîĵķļɱñöþǫŕšţûṽŵẋýž";
+/// The accented versions of the uppercase ASCII letters used by `pseudo_localize`.
+const ACCENTED_UPPER: &'static str = "ÅƁÇÐÉƑĜĤÎĴĶĻṀÑÖÞǪŔŜŢÛṼŴẊÝŽ";
+
+/// Returns the pseudo-localized version of the text.
+///
+/// The ASCII letters are replaced with accented letters, the text is lengthened by about a
+/// third with tildes and it is put in brackets, so `Sword` becomes `[Ŝŵöŕð~~]`. The texts that
+/// aren't rendered by the encyclopedia stand out, and the layouts can be tested with longer
+/// texts without translating anything.
+pub fn pseudo_localize(text: &str) -> String {
+    let mut out = String::from("[");
+    let mut len = 0;
+    for c in text.chars() {
+        let accented = match c {
+            c if c.is_ascii_lowercase() => ACCENTED_LOWER.chars().nth(c as usize - 'a' as usize),
+            c if c.is_ascii_uppercase() => ACCENTED_UPPER.chars().nth(c as usize - 'A' as usize),
+            _ => None,
+        };
+        out.push(accented.unwrap_or(c));
+        len += 1;
+    }
+    for _ in 0..(len * 3 + 9) / 10 {
+        out.push('~');
+    }
+    out.push(']');
+    out
+}
+
+/// The rendering of the words.
+///
+/// The translations are read from the `LingTag::CustomStr` tags with the keys made by
+/// `format::po::locale_key`, like in `Encyclopedia::coverage`. The texts are pseudo-localized
+/// if that is enabled with `Encyclopedia::set_pseudo_locale`.
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Returns the text of the name of the word in the locale.
+    ///
+    /// The text is the name itself if no locale is given or if the name isn't translated into
+    /// the locale. Fails if the word isn't found.
+    pub fn render_name(&self, name: &str, locale: Option<&str>) -> LingResult<String> {
+        self.render_form(name, locale, &[])
+    }
+
+    /// Returns the text of the form of the word with the grammemes in the locale.
+    ///
+    /// The text is the first form given by `Encyclopedia::inflect` if no locale is given or if
+    /// the form isn't translated into the locale. Fails if the word isn't found or if it has
+    /// no such form.
+    pub fn render_form(&self,
+                       name: &str,
+                       locale: Option<&str>,
+                       grammemes: &[Grammeme])
+                       -> LingResult<String> {
+        let word = try!(self.get_word(name).ok_or(LingError::NoSuchWord(name.to_owned())));
+        let translation = locale.and_then(|locale| {
+            word.get_tags().iter().find(|t| translates_form(t, locale, grammemes)).and_then(|t| {
+                match **t {
+                    LingTag::CustomStr(_, Some(ref text)) => Some(text.clone()),
+                    _ => None,
+                }
+            })
+        });
+        let text = match translation {
+            Some(text) => text,
+            None => {
+                let forms = try!(self.inflect(name, grammemes));
+                try!(forms.into_iter().next().ok_or(LingError::NoSuchForm(name.to_owned())))
+            }
+        };
+
+        if self.is_pseudo_locale_enabled() {
+            Ok(pseudo_localize(&text))
+        } else {
+            Ok(text)
+        }
+    }
+}
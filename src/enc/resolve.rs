@@ -0,0 +1,227 @@
+//! Contains the resolution of the effective tags a word inherits from it's parents and tag
+//! groups.
+use std::collections::{HashMap, HashSet};
+
+use enc::encyclopedia::GenericEncyclopedia;
+use enc::word::GenericWord;
+use enc::provenance::Provenance;
+use enc::ling::{LingTag, UserTag, NoUserTag};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The word or tag group a tag is defined in.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum TagSource {
+    Word(String),
+    TagGroup(String),
+    /// A modifier word applied on top of the word, see `Encyclopedia::explain_tags`.
+    Modifier(String),
+}
+
+/// A tag contributed to a word when resolving it's effective tags.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ResolvedTag<U = NoUserTag> {
+    pub tag: LingTag<U>,
+    /// Where the tag is defined.
+    pub source: TagSource,
+    /// Where the tag was removed or overridden, or `None` if the tag is effective.
+    pub removed_by: Option<TagSource>,
+    /// The inheritance path of the tag, starting from the resolved word and ending at the word
+    /// that has the tag or uses the tag group that has it.
+    pub path: Vec<String>,
+    /// The recorded provenance of the tag, if the encyclopedia tracks provenance and the tag
+    /// is defined in a word.
+    pub provenance: Option<Provenance>,
+}
+
+impl<U> ResolvedTag<U> {
+    /// Returns true if the tag is one of the effective tags of the word.
+    pub fn is_effective(&self) -> bool {
+        self.removed_by.is_none()
+    }
+}
+
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Resolves the effective tags of the word with the given name and returns every tag that
+    /// was contributed to it, in the order they were applied.
+    ///
+    /// The tags are applied in layers, and every layer can remove or override the tags of the
+    /// previous ones:
+    ///
+    /// 1. The effective tags of the parents given with `LingTag::Parent`, in the order of the
+    ///    parent names. The `Parent` and `UseTagGroup` tags of the parents aren't inherited.
+    /// 2. The tags of the groups given with `LingTag::UseTagGroup`, in the order of the group
+    ///    names.
+    /// 3. The word's own tags.
+    ///
+    /// Inside a layer, the `LingTag::Remove` tags are applied first and they remove the earlier
+    /// tags they override, see `LingTag::overrides`. Then the other tags are added, overriding
+    /// the earlier tags with the same keys.
+    /// The removed tags are included in the result with the source that removed them, which
+    /// tells where every effective tag came from and why the others are missing.
+    ///
+    /// Parents and tag groups that aren't stored are ignored. Fails if the word isn't found or
+    /// if it is it's own ancestor.
+    pub fn resolve_tags(&self, name: &str) -> LingResult<Vec<ResolvedTag<U>>> {
+        let word = try!(self.get_word(name).ok_or(LingError::NoSuchWord(name.to_owned())));
+        self.resolve_word(&word, &mut Vec::new(), &mut HashMap::new())
+    }
+
+    /// Explains where the tags of the word with the given name come from, with the modifier
+    /// words with the given names applied on top of it in order.
+    ///
+    /// Like `resolve_tags`, but the own tags of every modifier are applied as a layer after the
+    /// word's own tags, like `EncSysWorld::spawn_with` applies them. Every returned tag has the
+    /// inheritance path and the recorded provenance telling where it was defined, see
+    /// `Encyclopedia::enable_provenance`.
+    pub fn explain_tags<T: AsRef<str>>(&self,
+                                       name: &str,
+                                       modifiers: &[T])
+                                       -> LingResult<Vec<ResolvedTag<U>>> {
+        let mut resolved = try!(self.resolve_tags(name));
+        for mod_name in modifiers {
+            let mod_name = mod_name.as_ref();
+            let modifier = try!(self.get_word(mod_name)
+                .ok_or(LingError::NoSuchWord(mod_name.to_owned())));
+            let mod_name = modifier.get_name().into_owned();
+            let tags = modifier.get_tags().iter().map(|t| t.clone().into_owned());
+            self.apply_layer(&mut resolved, tags, TagSource::Modifier(mod_name.clone()), &mod_name);
+        }
+        Ok(resolved)
+    }
+
+    /// Returns the effective tags of the word with the given name, see `resolve_tags`.
+    pub fn effective_tags(&self, name: &str) -> LingResult<HashSet<LingTag<U>>> {
+        let resolved = try!(self.resolve_tags(name));
+        Ok(resolved.into_iter().filter(ResolvedTag::is_effective).map(|r| r.tag).collect())
+    }
+
+    /// Returns the word with the given name with it's effective tags, see `resolve_tags`.
+    ///
+    /// `EncSysWorld` spawns the stored words without resolving them, so a word is spawned with
+    /// it's effective tags by giving this to `EncSysWorld::entity_from_word`.
+    pub fn get_effective_word(&self, name: &str) -> LingResult<GenericWord<'static, U>> {
+        let tags = try!(self.effective_tags(name));
+        let name = self.resolve_name(name).unwrap().to_owned();
+        Ok(GenericWord::new_from_collection(name, tags))
+    }
+
+    /// Resolves the tags of a word, `visiting` has the names of the words being resolved.
+    ///
+    /// The resolved ancestors are kept in `cache`, so an ancestor shared by many parents is
+    /// resolved only once.
+    fn resolve_word(&self,
+                    word: &GenericWord<U>,
+                    visiting: &mut Vec<String>,
+                    cache: &mut HashMap<String, Vec<ResolvedTag<U>>>)
+                    -> LingResult<Vec<ResolvedTag<U>>> {
+        let name = word.get_name().into_owned();
+        if visiting.contains(&name) {
+            return Err(LingError::ParentCycle(name));
+        }
+        visiting.push(name.clone());
+
+        let mut parents = Vec::new();
+        let mut groups = Vec::new();
+        for tag in word.get_tags() {
+            match **tag {
+                LingTag::Parent(ref parent) => parents.push(parent.clone()),
+                LingTag::UseTagGroup(ref group) => groups.push(group.clone()),
+                _ => (),
+            }
+        }
+        parents.sort();
+        groups.sort();
+
+        let mut resolved: Vec<ResolvedTag<U>> = Vec::new();
+        for parent in parents {
+            let parent = match self.get_word(&parent as &str) {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let parent_name = parent.get_name().into_owned();
+            if !cache.contains_key(&parent_name) {
+                let parent_tags = try!(self.resolve_word(&parent, visiting, cache));
+                cache.insert(parent_name.clone(), parent_tags);
+            }
+            for entry in cache[&parent_name].iter().cloned() {
+                match entry.tag {
+                    LingTag::Parent(_) |
+                    LingTag::UseTagGroup(_) => continue,
+                    _ => (),
+                }
+                // a common ancestor of many parents is only inherited once
+                if resolved.iter().any(|r| {
+                    r.tag == entry.tag && r.source == entry.source &&
+                    r.removed_by == entry.removed_by
+                }) {
+                    continue;
+                }
+                let mut entry = entry;
+                entry.path.insert(0, name.clone());
+                if entry.is_effective() {
+                    override_tags(&mut resolved, &entry.tag, &entry.source);
+                }
+                resolved.push(entry);
+            }
+        }
+
+        for group in groups {
+            if let Some(tags) = self.get_tag_group(&group) {
+                let tags = tags.iter().cloned();
+                self.apply_layer(&mut resolved, tags, TagSource::TagGroup(group), &name);
+            }
+        }
+        let own_tags = word.get_tags().iter().map(|t| t.clone().into_owned());
+        self.apply_layer(&mut resolved, own_tags, TagSource::Word(name.clone()), &name);
+
+        visiting.pop();
+        Ok(resolved)
+    }
+
+    /// Applies the tags of a layer from the source, `word` is the name of the word that has
+    /// the tags or uses their tag group.
+    fn apply_layer<I>(&self,
+                      resolved: &mut Vec<ResolvedTag<U>>,
+                      tags: I,
+                      source: TagSource,
+                      word: &str)
+        where I: IntoIterator<Item = LingTag<U>>
+    {
+        let tags: Vec<LingTag<U>> = tags.into_iter().collect();
+        for tag in &tags {
+            if let LingTag::Remove(ref removed) = *tag {
+                override_tags(resolved, removed, &source);
+            }
+        }
+        for tag in tags {
+            if let LingTag::Remove(_) = tag {
+                continue;
+            }
+            override_tags(resolved, &tag, &source);
+            let provenance = match source {
+                TagSource::TagGroup(_) => None,
+                _ => self.get_tag_provenance(word, &tag).cloned(),
+            };
+            resolved.push(ResolvedTag {
+                tag: tag,
+                source: source.clone(),
+                removed_by: None,
+                path: vec![word.to_owned()],
+                provenance: provenance,
+            });
+        }
+    }
+}
+
+/// Marks the effective tags overridden by the tag as removed by the source.
+fn override_tags<U: UserTag>(resolved: &mut [ResolvedTag<U>],
+                             tag: &LingTag<U>,
+                             source: &TagSource) {
+    for entry in resolved.iter_mut().filter(|r| r.is_effective()) {
+        if tag.overrides(&entry.tag) {
+            entry.removed_by = Some(source.clone());
+        }
+    }
+}
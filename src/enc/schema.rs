@@ -0,0 +1,230 @@
+//! Contains the `TagSchema` struct for declaring the allowed custom tag keys of an encyclopedia.
+use std::collections::{HashMap, HashSet, hash_map};
+
+use enc::word::GenericWord;
+use enc::ling::{LingTag, TagValue, UserTag};
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// The kind of tag and value a declared key accepts.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ValueType {
+    /// A `LingTag::Custom` tag without a value.
+    Flag,
+    /// A `LingTag::CustomStr` tag or a string `LingTag::Value`.
+    Str,
+    /// A `LingTag::CustomVec` or a `LingTag::CustomSet` tag.
+    StrList,
+    Int,
+    /// A float or an integer value.
+    Float,
+    Bool,
+    IntRange,
+    /// A float or an integer range.
+    FloatRange,
+    List,
+}
+
+impl ValueType {
+    /// Returns the name of the type.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ValueType::Flag => "flag",
+            ValueType::Str => "string",
+            ValueType::StrList => "string list",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+            ValueType::IntRange => "int range",
+            ValueType::FloatRange => "float range",
+            ValueType::List => "list",
+        }
+    }
+
+    /// Returns true if the value is of this type.
+    pub fn accepts_value(&self, value: &TagValue) -> bool {
+        match (*self, value) {
+            (ValueType::Str, &TagValue::Str(_)) |
+            (ValueType::Int, &TagValue::Int(_)) |
+            (ValueType::Float, &TagValue::Float(_)) |
+            (ValueType::Float, &TagValue::Int(_)) |
+            (ValueType::Bool, &TagValue::Bool(_)) |
+            (ValueType::IntRange, &TagValue::IntRange(..)) |
+            (ValueType::FloatRange, &TagValue::FloatRange(..)) |
+            (ValueType::FloatRange, &TagValue::IntRange(..)) |
+            (ValueType::List, &TagValue::List(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the tag is of this type.
+    pub fn accepts<U>(&self, tag: &LingTag<U>) -> bool {
+        match (*self, tag) {
+            (ValueType::Flag, &LingTag::Custom(_)) |
+            (ValueType::Str, &LingTag::CustomStr(..)) |
+            (ValueType::StrList, &LingTag::CustomVec(..)) |
+            (ValueType::StrList, &LingTag::CustomSet(..)) => true,
+            (_, &LingTag::Value(_, ref value)) => self.accepts_value(value),
+            _ => false,
+        }
+    }
+}
+
+/// The declaration of a custom tag key.
+#[derive(Clone, Debug)]
+pub struct KeySchema {
+    value_type: ValueType,
+    required_for: HashSet<String>,
+    default: Option<TagValue>,
+}
+
+impl KeySchema {
+    /// Declares a key whose tags have the given type.
+    pub fn new(value_type: ValueType) -> Self {
+        KeySchema {
+            value_type: value_type,
+            required_for: HashSet::new(),
+            default: None,
+        }
+    }
+
+    /// Makes the key required for the words with a `LingTag::Type` tag of the given type.
+    pub fn required_for<T: Into<String>>(mut self, word_type: T) -> Self {
+        self.required_for.insert(word_type.into());
+        self
+    }
+
+    /// Sets the default value of the key, which is given to the words that don't have the key
+    /// with `TagSchema::apply_defaults`, which `Encyclopedia::apply_schema` does when checking.
+    ///
+    /// A required key with a default value is never missing.
+    pub fn with_default(mut self, value: TagValue) -> Self {
+        self.default = Some(value);
+        self
+    }
+
+    pub fn get_value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    pub fn get_default(&self) -> Option<&TagValue> {
+        self.default.as_ref()
+    }
+
+    /// Returns true if the key is required for words of the given type.
+    pub fn is_required_for(&self, word_type: &str) -> bool {
+        self.required_for.contains(word_type)
+    }
+}
+
+/// A list of the custom tag keys allowed in an encyclopedia.
+///
+/// Every custom tag and value tag of a checked word must use a declared key with a tag of the
+/// declared type, and the words of a type must have the keys required for it.
+/// See `Encyclopedia::set_schema`.
+#[derive(Clone, Debug, Default)]
+pub struct TagSchema {
+    keys: HashMap<String, KeySchema>,
+}
+
+impl TagSchema {
+    /// Creates an empty schema that allows no custom tags.
+    pub fn new() -> Self {
+        TagSchema { keys: HashMap::new() }
+    }
+
+    /// Declares a key, replacing any previous declaration of it.
+    ///
+    /// Fails if the default value of the key doesn't have the declared type.
+    pub fn declare<T: Into<String>>(&mut self, key: T, schema: KeySchema) -> LingResult<()> {
+        let key = key.into();
+        if let Some(ref default) = schema.default {
+            if !schema.value_type.accepts_value(default) {
+                return Err(LingError::WrongValueType(key, schema.value_type.name()));
+            }
+        }
+        self.keys.insert(key, schema);
+        Ok(())
+    }
+
+    /// Returns the declaration of the key.
+    pub fn get(&self, key: &str) -> Option<&KeySchema> {
+        self.keys.get(key)
+    }
+
+    /// Removes the declaration of the key.
+    pub fn undeclare(&mut self, key: &str) {
+        self.keys.remove(key);
+    }
+
+    /// Returns an iterator over the declared keys.
+    pub fn iter(&self) -> hash_map::Iter<String, KeySchema> {
+        self.keys.iter()
+    }
+
+    /// Checks that the tags use only declared keys with the right types.
+    ///
+    /// The keys of the tags removed with `LingTag::Remove` have to be declared too, but their
+    /// types aren't checked.
+    pub fn check_tag<U: UserTag>(&self, tag: &LingTag<U>) -> LingResult<()> {
+        let (tag, check_type) = match *tag {
+            LingTag::Remove(ref t) => (&**t, false),
+            ref t => (t, true),
+        };
+        let key = match tag.key() {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        match self.keys.get(key) {
+            None => Err(LingError::UnknownTagKey(key.to_owned())),
+            Some(schema) if check_type && !schema.value_type.accepts(tag) => {
+                Err(LingError::WrongValueType(key.to_owned(), schema.value_type.name()))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Checks that the tags have all of the keys required by their types.
+    pub fn check_required<'a, U, I>(&self, tags: I) -> LingResult<()>
+        where U: UserTag,
+              I: IntoIterator<Item = &'a LingTag<U>>
+    {
+        let mut types = Vec::new();
+        let mut keys = HashSet::new();
+        for tag in tags {
+            match *tag {
+                LingTag::Type(ref t) => types.push(t as &str),
+                ref t => keys.extend(t.key()),
+            }
+        }
+        // sorted so that the same word always gives the same error
+        let mut missing: Vec<&str> = self.keys
+            .iter()
+            .filter(|&(key, schema)| {
+                !keys.contains(key as &str) && schema.default.is_none() &&
+                types.iter().any(|t| schema.is_required_for(t))
+            })
+            .map(|(key, _)| key as &str)
+            .collect();
+        missing.sort();
+        match missing.first() {
+            Some(key) => Err(LingError::MissingTagKey((*key).to_owned())),
+            None => Ok(()),
+        }
+    }
+
+    /// Gives the word the default values of the keys it doesn't have.
+    pub fn apply_defaults<U: UserTag>(&self, word: &mut GenericWord<U>) {
+        let missing: Vec<LingTag<U>> = self.keys
+            .iter()
+            .filter(|&(key, _)| !word.get_tags().iter().any(|t| t.key() == Some(key)))
+            .filter_map(|(key, schema)| {
+                schema.default.as_ref().map(|v| LingTag::Value(key.clone(), v.clone()))
+            })
+            .collect();
+        for tag in missing {
+            word.add_tag(tag.into());
+        }
+    }
+}
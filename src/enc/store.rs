@@ -0,0 +1,325 @@
+//! Contains the `WordId` handle, the string interner and the internal representation of the
+//! stored tags.
+use std::collections::HashMap;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::hash::{Hash, Hasher};
+
+use enc::ling::{LingTag, TagValue, UserTag};
+
+/// A stable numeric handle to a word name in an `Encyclopedia`.
+///
+/// Every word name gets an id when it's first added or referred to by a tag, and the id never
+/// changes or gets reused, even if the word is renamed or removed. This makes the ids usable in
+/// save files and as cheap back-references to the words. The ids depend on the order the names
+/// were added in, so an encyclopedia loaded from the same words in another order has other ids,
+/// and the files shared between encyclopedias should use the names instead.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct WordId(u32);
+
+impl WordId {
+    /// Returns the id as an index number.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    /// Creates an id from an index number returned by `index`.
+    ///
+    /// Returns `None` if the index is too large to be an id.
+    pub fn from_index(index: usize) -> Option<WordId> {
+        if index <= u32::max_value() as usize {
+            Some(WordId(index as u32))
+        } else {
+            None
+        }
+    }
+}
+
+/// A handle to an interned string in a `WordTable`.
+///
+/// Comparing and hashing symbols is cheap, and every distinct string is stored only once no
+/// matter how many tags use it.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct Symbol(u32);
+
+/// Stores every distinct string once and gives them symbols.
+///
+/// The strings are found by their hashes, so the index doesn't need copies of them.
+struct Interner {
+    strings: Vec<Box<str>>,
+    /// The symbols of the strings by their hashes.
+    symbols: HashMap<u64, Symbol>,
+    /// The symbols of the strings whose hash was already taken by another string.
+    collisions: Vec<Symbol>,
+}
+
+impl Interner {
+    fn new() -> Self {
+        Interner {
+            strings: Vec::new(),
+            symbols: HashMap::new(),
+            collisions: Vec::new(),
+        }
+    }
+
+    fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(sym) = self.get(s) {
+            return sym;
+        }
+        assert!(self.strings.len() <= u32::max_value() as usize,
+                "too many interned strings");
+        let sym = Symbol(self.strings.len() as u32);
+        self.strings.push(s.into());
+        match self.symbols.entry(hash(s)) {
+            Entry::Occupied(_) => self.collisions.push(sym),
+            Entry::Vacant(entry) => {
+                entry.insert(sym);
+            }
+        }
+        sym
+    }
+
+    fn get(&self, s: &str) -> Option<Symbol> {
+        match self.symbols.get(&hash(s)) {
+            Some(&sym) if self.resolve(sym) == s => Some(sym),
+            Some(_) => self.collisions.iter().find(|&&sym| self.resolve(sym) == s).cloned(),
+            None => None,
+        }
+    }
+
+    fn resolve(&self, sym: Symbol) -> &str {
+        &self.strings[sym.0 as usize]
+    }
+}
+
+fn hash(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The interned strings, and the names of the words and their ids.
+pub struct WordTable {
+    strings: Interner,
+    names: Vec<Symbol>,
+    ids: HashMap<Symbol, WordId>,
+}
+
+impl WordTable {
+    pub fn new() -> Self {
+        WordTable {
+            strings: Interner::new(),
+            names: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Returns the id of the name, giving it a new id if it doesn't have one yet.
+    pub fn intern(&mut self, name: &str) -> WordId {
+        let sym = self.symbol(name);
+        if let Some(&id) = self.ids.get(&sym) {
+            return id;
+        }
+        let id = WordId::from_index(self.names.len()).expect("too many word names");
+        self.names.push(sym);
+        self.ids.insert(sym, id);
+        id
+    }
+
+    /// Returns the id of the name if it has one.
+    pub fn get(&self, name: &str) -> Option<WordId> {
+        self.strings.get(name).and_then(|sym| self.ids.get(&sym)).cloned()
+    }
+
+    /// Returns the name of the id.
+    pub fn name(&self, id: WordId) -> &str {
+        self.strings.resolve(self.names[id.index()])
+    }
+
+    /// Changes the name of the id.
+    /// The old name won't have an id anymore and the id the new name had before is forgotten.
+    pub fn rename(&mut self, id: WordId, new: &str) {
+        let sym = self.symbol(new);
+        self.ids.remove(&self.names[id.index()]);
+        self.ids.insert(sym, id);
+        self.names[id.index()] = sym;
+    }
+
+    /// Returns the amount of ids given.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns the symbol of the string, interning it if needed.
+    pub fn symbol(&mut self, s: &str) -> Symbol {
+        self.strings.intern(s)
+    }
+
+    /// Returns the symbol of the string if it has been interned.
+    pub fn find_symbol(&self, s: &str) -> Option<Symbol> {
+        self.strings.get(s)
+    }
+
+    /// Returns the string of the symbol.
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        self.strings.resolve(sym)
+    }
+
+    /// Returns the amount of distinct strings interned.
+    pub fn symbol_amount(&self) -> usize {
+        self.strings.strings.len()
+    }
+}
+
+/// A tag in the form it's stored inside an encyclopedia, with the strings interned and the word
+/// references as ids.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum StoredTag<U> {
+    Type(Symbol),
+    UseTagGroup(Symbol),
+    Parent(WordId),
+    HasWords(Vec<WordId>),
+    Synonym(WordId),
+    Relation(Symbol, WordId),
+    Custom(Symbol),
+    CustomStr(Symbol, Option<Symbol>),
+    CustomVec(Symbol, Vec<Symbol>),
+    CustomSet(Symbol, Vec<Symbol>),
+    Value(Symbol, TagValue),
+    Remove(Box<StoredTag<U>>),
+    User(U),
+}
+
+impl<U: UserTag> StoredTag<U> {
+    /// Converts a tag into the stored form, interning the strings and giving ids to the referred
+    /// words.
+    pub fn store(tag: &LingTag<U>, words: &mut WordTable) -> StoredTag<U> {
+        match tag {
+            &LingTag::Type(ref s) => StoredTag::Type(words.symbol(s)),
+            &LingTag::UseTagGroup(ref s) => StoredTag::UseTagGroup(words.symbol(s)),
+            &LingTag::Parent(ref s) => StoredTag::Parent(words.intern(s)),
+            &LingTag::HasWords(ref v) => {
+                StoredTag::HasWords(v.iter().map(|s| words.intern(s)).collect())
+            }
+            &LingTag::Synonym(ref s) => StoredTag::Synonym(words.intern(s)),
+            &LingTag::Relation(ref r, ref s) => {
+                StoredTag::Relation(words.symbol(r), words.intern(s))
+            }
+            &LingTag::Custom(ref s) => StoredTag::Custom(words.symbol(s)),
+            &LingTag::CustomStr(ref k, ref v) => {
+                StoredTag::CustomStr(words.symbol(k), v.as_ref().map(|v| words.symbol(v)))
+            }
+            &LingTag::CustomVec(ref k, ref v) => {
+                StoredTag::CustomVec(words.symbol(k), v.iter().map(|s| words.symbol(s)).collect())
+            }
+            // the set is kept in the order of the strings, so equal sets are stored equally
+            &LingTag::CustomSet(ref k, ref v) => {
+                StoredTag::CustomSet(words.symbol(k), v.iter().map(|s| words.symbol(s)).collect())
+            }
+            &LingTag::Value(ref k, ref v) => StoredTag::Value(words.symbol(k), v.clone()),
+            &LingTag::Remove(ref t) => StoredTag::Remove(Box::new(StoredTag::store(t, words))),
+            &LingTag::User(ref u) => StoredTag::User(u.clone()),
+        }
+    }
+
+    /// Converts a tag into the stored form without interning new strings or giving new ids.
+    ///
+    /// Returns `None` if the tag has a string that isn't interned or refers to a word that
+    /// doesn't have an id, since no such tag can be stored.
+    pub fn find(tag: &LingTag<U>, words: &WordTable) -> Option<StoredTag<U>> {
+        let sym = |s: &String| words.find_symbol(s);
+        let syms = |v: &mut dyn Iterator<Item = &String>| v.map(&sym).collect::<Option<Vec<_>>>();
+
+        match tag {
+            &LingTag::Type(ref s) => sym(s).map(StoredTag::Type),
+            &LingTag::UseTagGroup(ref s) => sym(s).map(StoredTag::UseTagGroup),
+            &LingTag::Parent(ref s) => words.get(s).map(StoredTag::Parent),
+            &LingTag::HasWords(ref v) => {
+                v.iter().map(|s| words.get(s)).collect::<Option<Vec<_>>>().map(StoredTag::HasWords)
+            }
+            &LingTag::Synonym(ref s) => words.get(s).map(StoredTag::Synonym),
+            &LingTag::Relation(ref r, ref s) => {
+                sym(r).and_then(|r| words.get(s).map(|id| StoredTag::Relation(r, id)))
+            }
+            &LingTag::Custom(ref s) => sym(s).map(StoredTag::Custom),
+            &LingTag::CustomStr(ref k, None) => sym(k).map(|k| StoredTag::CustomStr(k, None)),
+            &LingTag::CustomStr(ref k, Some(ref v)) => {
+                sym(k).and_then(|k| sym(v).map(|v| StoredTag::CustomStr(k, Some(v))))
+            }
+            &LingTag::CustomVec(ref k, ref v) => {
+                sym(k).and_then(|k| syms(&mut v.iter()).map(|v| StoredTag::CustomVec(k, v)))
+            }
+            &LingTag::CustomSet(ref k, ref v) => {
+                sym(k).and_then(|k| syms(&mut v.iter()).map(|v| StoredTag::CustomSet(k, v)))
+            }
+            &LingTag::Value(ref k, ref v) => sym(k).map(|k| StoredTag::Value(k, v.clone())),
+            &LingTag::Remove(ref t) => {
+                StoredTag::find(t, words).map(|t| StoredTag::Remove(Box::new(t)))
+            }
+            &LingTag::User(ref u) => Some(StoredTag::User(u.clone())),
+        }
+    }
+
+    /// Converts the stored tag back into a `LingTag`.
+    pub fn load(&self, words: &WordTable) -> LingTag<U> {
+        let string = |&sym: &Symbol| words.resolve(sym).to_owned();
+
+        match self {
+            &StoredTag::Type(ref s) => LingTag::Type(string(s)),
+            &StoredTag::UseTagGroup(ref s) => LingTag::UseTagGroup(string(s)),
+            &StoredTag::Parent(id) => LingTag::Parent(words.name(id).to_owned()),
+            &StoredTag::HasWords(ref v) => {
+                LingTag::HasWords(v.iter().map(|&id| words.name(id).to_owned()).collect())
+            }
+            &StoredTag::Synonym(id) => LingTag::Synonym(words.name(id).to_owned()),
+            &StoredTag::Relation(ref r, id) => {
+                LingTag::Relation(string(r), words.name(id).to_owned())
+            }
+            &StoredTag::Custom(ref s) => LingTag::Custom(string(s)),
+            &StoredTag::CustomStr(ref k, ref v) => {
+                LingTag::CustomStr(string(k), v.as_ref().map(string))
+            }
+            &StoredTag::CustomVec(ref k, ref v) => {
+                LingTag::CustomVec(string(k), v.iter().map(string).collect())
+            }
+            &StoredTag::CustomSet(ref k, ref v) => {
+                LingTag::CustomSet(string(k), v.iter().map(string).collect())
+            }
+            &StoredTag::Value(ref k, ref v) => LingTag::Value(string(k), v.clone()),
+            &StoredTag::Remove(ref t) => LingTag::Remove(Box::new(t.load(words))),
+            &StoredTag::User(ref u) => LingTag::User(u.clone()),
+        }
+    }
+
+    /// Returns true if the tag refers to the word with the given id.
+    pub fn refers_to(&self, id: WordId) -> bool {
+        match self {
+            &StoredTag::Parent(i) |
+            &StoredTag::Synonym(i) |
+            &StoredTag::Relation(_, i) => i == id,
+            &StoredTag::HasWords(ref v) => v.contains(&id),
+            &StoredTag::Remove(ref t) => t.refers_to(id),
+            _ => false,
+        }
+    }
+
+    /// Returns the tag with the references to the word `old` changed to `new`, or `None` if the
+    /// tag doesn't refer to `old`.
+    pub fn replace_ref(&self, old: WordId, new: WordId) -> Option<StoredTag<U>> {
+        let swap = |i: WordId| if i == old { new } else { i };
+        if !self.refers_to(old) {
+            return None;
+        }
+        Some(match self {
+            &StoredTag::Parent(i) => StoredTag::Parent(swap(i)),
+            &StoredTag::Synonym(i) => StoredTag::Synonym(swap(i)),
+            &StoredTag::Relation(r, i) => StoredTag::Relation(r, swap(i)),
+            &StoredTag::HasWords(ref v) => {
+                StoredTag::HasWords(v.iter().map(|&i| swap(i)).collect())
+            }
+            &StoredTag::Remove(ref t) => {
+                StoredTag::Remove(Box::new(t.replace_ref(old, new).unwrap()))
+            }
+            _ => unreachable!(),
+        })
+    }
+}
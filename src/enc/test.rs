@@ -1,5 +1,6 @@
 use super::*;
-use super::ling::LingTag;
+use super::ling::{LingTag, LingError, TagValue};
+use super::ling::grammeme::GrammCategory;
 
 #[test]
 fn add_then_check_word() {
@@ -74,3 +75,759 @@ fn test_get() {
     assert_eq!(None, enc.get_word("word2".to_string()));
     assert_eq!(Some(word3), enc.get_word("word3".to_string()));
 }
+
+#[test]
+fn apply_modifiers() {
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+
+    let mut word = Word::new_from_collection("goblin", vec![tag("green"), tag("small")]);
+    let giant = Word::new_from_collection("giant",
+                                          vec![LingTag::Remove(Box::new(tag("small"))),
+                                               tag("big")]);
+    let tiny = Word::new_from_collection("tiny",
+                                         vec![LingTag::Remove(Box::new(tag("big"))),
+                                              tag("small")]);
+
+    word.apply_modifier(&giant);
+    assert!(word.has_tag(&tag("big")));
+    assert!(!word.has_tag(&tag("small")));
+
+    // the later modifier wins
+    word.apply_modifier(&tiny);
+    assert!(word.has_tag(&tag("small")));
+    assert!(!word.has_tag(&tag("big")));
+    assert_eq!(word.tag_amount(), 2);
+}
+
+#[test]
+fn change_log() {
+    let mut enc = Encyclopedia::new();
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+
+    // nothing is recorded before enabling the log
+    enc.add_word(Word::new("word1"));
+    enc.enable_change_log();
+
+    enc.add_word(Word::new_from_collection("word1", vec![tag("a")]));
+    enc.add_word(Word::new_from_collection("word2", vec![tag("b")]));
+    enc.remove_word("word1");
+    enc.remove_word("none");
+
+    assert_eq!(enc.drain_changes().collect::<Vec<_>>(),
+               vec![EncChange::WordReplaced("word1".to_owned()),
+                    EncChange::TagAdded("word1".to_owned(), tag("a")),
+                    EncChange::WordAdded("word2".to_owned()),
+                    EncChange::WordRemoved("word1".to_owned())]);
+    assert_eq!(enc.drain_changes().count(), 0);
+}
+
+#[test]
+fn transactions() {
+    let mut enc = Encyclopedia::new();
+    let word1 = Word::new_from_collection("word1", vec![LingTag::UseTagGroup("group".to_owned())]);
+
+    {
+        let mut tx = enc.begin_transaction();
+        tx.add_tag_group("group", vec![LingTag::Custom("a".to_owned())]).unwrap();
+        tx.add_word(word1.clone()).unwrap();
+        tx.add_gramm_cat(GrammCategory::new("case", Some("nom"), vec!["gen"])).unwrap();
+        tx.commit().unwrap();
+    }
+    {
+        let mut tx = enc.begin_transaction();
+        tx.remove_word("word1").unwrap();
+        tx.add_word(Word::new("word2")).unwrap();
+        tx.commit().unwrap();
+    }
+    assert_eq!(enc.get_word("word1"), None);
+
+    assert!(enc.undo());
+    assert_eq!(enc.get_word("word1"), Some(word1.clone()));
+    assert_eq!(enc.get_word("word2"), None);
+
+    assert!(enc.undo());
+    assert!(enc.is_empty());
+    assert_eq!(enc.tag_group_amount(), 0);
+    assert!(!enc.undo());
+
+    assert!(enc.redo());
+    assert_eq!(enc.get_word("word1"), Some(word1));
+    assert_eq!(enc.gramm_cat_amount(), 1);
+    assert!(enc.can_redo());
+
+    // the group is used by word1
+    match enc.remove_tag_group("group") {
+        Err(LingError::TagGroupInUse(ref group)) if group == "group" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(enc.get_tag_group("group").is_some());
+
+    // undoing and redoing restores the words exactly as they were
+    enc.add_word(Word::new("word3"));
+    enc.add_word(Word::new("word4"));
+    enc.rename_word("word3", "word5", true).unwrap();
+    enc.enable_change_log();
+    let revision = enc.word_revision("word5");
+    let order: Vec<String> = enc.iter_words().map(|w| w.get_name().into_owned()).collect();
+    {
+        let mut tx = enc.begin_transaction();
+        tx.remove_word("word5").unwrap();
+        tx.commit().unwrap();
+    }
+    assert!(enc.undo());
+    assert_eq!(enc.word_revision("word5"), revision);
+    assert_eq!(enc.resolve_name("word3"), Some("word5"));
+    assert_eq!(enc.iter_words().map(|w| w.get_name().into_owned()).collect::<Vec<_>>(), order);
+    assert!(enc.redo());
+    assert_eq!(enc.resolve_name("word3"), None);
+    assert_eq!(enc.drain_changes().collect::<Vec<_>>(),
+               vec![EncChange::WordRemoved("word5".to_owned()),
+                    EncChange::WordAdded("word5".to_owned()),
+                    EncChange::WordRemoved("word5".to_owned())]);
+}
+
+#[test]
+fn failed_transaction() {
+    let mut enc = Encyclopedia::new();
+    enc.add_word(Word::new("word1"));
+
+    {
+        let mut tx = enc.begin_transaction();
+        tx.remove_word("word1").unwrap();
+        tx.add_word(Word::new("word2")).unwrap();
+        // the tag group doesn't exist
+        let bad_word = Word::new_from_collection("word3",
+                                                 vec![LingTag::UseTagGroup("none".to_owned())]);
+        assert!(tx.add_word(bad_word).is_err());
+        assert!(tx.add_word(Word::new("word4")).is_err());
+        assert!(tx.commit().is_err());
+    }
+
+    assert_eq!(enc.word_amount(), 1);
+    assert!(enc.get_word("word1").is_some());
+    assert!(!enc.can_undo());
+
+    // dropping without committing rolls back
+    enc.begin_transaction().remove_word("word1").unwrap();
+    assert!(enc.get_word("word1").is_some());
+
+    // rolling back leaves no trace
+    enc.add_tag_group("group", vec![LingTag::Custom("a".to_owned())]);
+    let word2 = Word::new_from_collection("word2", vec![LingTag::UseTagGroup("group".to_owned())]);
+    enc.add_word(word2);
+    enc.enable_provenance();
+    enc.add_word(Word::new_from_collection("word0", vec![LingTag::Custom("a".to_owned())]));
+    enc.remove_word("word1");
+    enc.rename_word("word0", "word1", true).unwrap();
+    let provenance = Provenance { line: Some(2), ..Provenance::default() };
+    enc.set_tag_provenance("word1", &LingTag::Custom("a".to_owned()), provenance.clone())
+        .unwrap();
+    enc.enable_change_log();
+    let revision = enc.word_revision("word1");
+    let order: Vec<String> = enc.iter_words().map(|w| w.get_name().into_owned()).collect();
+    {
+        let mut tx = enc.begin_transaction();
+        tx.add_word(Word::new_from_collection("word1", vec![LingTag::Custom("b".to_owned())]))
+            .unwrap();
+        tx.remove_word("word1").unwrap();
+        tx.add_word(Word::new("word1")).unwrap();
+        // the group is still used by word2
+        match tx.remove_tag_group("group") {
+            Err(LingError::TagGroupInUse(ref group)) if group == "group" => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+    assert_eq!(enc.word_revision("word1"), revision);
+    assert_eq!(enc.iter_words().map(|w| w.get_name().into_owned()).collect::<Vec<_>>(), order);
+    assert_eq!(enc.drain_changes().count(), 0);
+    assert!(enc.get_tag_group("group").is_some());
+    assert_eq!(enc.resolve_name("word0"), Some("word1"));
+    assert_eq!(enc.get_tag_provenance("word1", &LingTag::Custom("a".to_owned())),
+               Some(&provenance));
+}
+
+#[test]
+fn modify_stored_word() {
+    let mut enc = Encyclopedia::new();
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+    enc.add_word(Word::new_from_collection("word1", vec![tag("a"), tag("b")]));
+    enc.add_word(Word::new("word2"));
+
+    assert_eq!(enc.add_word_tag("word1", tag("c")).unwrap(), true);
+    assert_eq!(enc.add_word_tag("word1", tag("c")).unwrap(), false);
+    assert_eq!(enc.remove_word_tag("word1", &tag("a")).unwrap(), true);
+    assert!(enc.add_word_tag("none", tag("c")).is_err());
+    assert!(enc.add_word_tag("word1", LingTag::UseTagGroup("none".to_owned())).is_err());
+
+    // uppercase the tag "b" and remove the tag "c"
+    let uppercase_b = |t: &LingTag| if t == &tag("b") { Some(tag("B")) } else { None };
+    let modified = enc.modify_word_tags("word1", |t| t != &tag("x"), uppercase_b).unwrap();
+    assert_eq!(modified, 2);
+
+    assert!(enc.rename_word("word1", "word2", false).is_err());
+    enc.rename_word("word1", "word3", false).unwrap();
+    assert_eq!(enc.get_word("word1"), None);
+    assert_eq!(enc.get_word("word3"), Some(Word::new_from_collection("word3", vec![tag("B")])));
+}
+
+#[test]
+fn rename_references() {
+    let mut enc = Encyclopedia::new();
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+    enc.add_tag_group("group", vec![LingTag::Synonym("sword".to_owned())]);
+    enc.add_word(Word::new("sword"));
+    enc.add_word(Word::new_from_collection("saber",
+                                           vec![LingTag::Parent("sword".to_owned()),
+                                                LingTag::UseTagGroup("group".to_owned())]));
+    enc.add_word(Word::new_from_collection("rack",
+                                           vec![LingTag::HasWords(vec!["axe".to_owned(),
+                                                                       "sword".to_owned()])]));
+    enc.add_word(Word::new_from_collection("axe", vec![tag("sword")]));
+
+    let report = enc.rename_word("sword", "blade", true).unwrap();
+    assert_eq!(report.words, vec!["rack".to_owned(), "saber".to_owned()]);
+    assert_eq!(report.tag_groups, vec!["group".to_owned()]);
+
+    assert!(enc.get_word("saber").unwrap().has_tag(&LingTag::Parent("blade".to_owned())));
+    assert!(enc.get_word("rack")
+        .unwrap()
+        .has_tag(&LingTag::HasWords(vec!["axe".to_owned(), "blade".to_owned()])));
+    assert!(enc.get_tag_group("group").unwrap().contains(&LingTag::Synonym("blade".to_owned())));
+
+    // the old name is an alias
+    assert_eq!(enc.get_word("sword"), Some(Word::new("blade")));
+    assert_eq!(enc.resolve_name("sword"), Some("blade"));
+    enc.remove_alias("sword");
+    assert_eq!(enc.get_word("sword"), None);
+
+    let report = enc.rename_tag_group("group", "weapons").unwrap();
+    assert_eq!(report.words, vec!["saber".to_owned()]);
+    assert!(enc.get_word("saber")
+        .unwrap()
+        .has_tag(&LingTag::UseTagGroup("weapons".to_owned())));
+}
+
+#[test]
+fn word_ids() {
+    let mut enc = Encyclopedia::new();
+    let id1 = enc.add_word(Word::new_from_collection("word1",
+                                                     vec![LingTag::Parent("word2".to_owned())]));
+    // referring to a word gives it an id, but it's only found when the word is stored
+    assert_eq!(enc.word_id("word2"), None);
+    let id2 = Some(enc.add_word(Word::new("word2")));
+    assert!(id2.unwrap() != id1);
+
+    assert_eq!(enc.word_id("word1"), Some(id1));
+    assert_eq!(enc.get_word_by_id(id1), enc.get_word("word1"));
+
+    // the id stays the same when the word is renamed or replaced
+    enc.rename_word("word2", "word3", false).unwrap();
+    assert_eq!(enc.word_id("word3"), id2);
+    assert_eq!(enc.word_name(id2.unwrap()), Some("word3"));
+    assert_eq!(enc.add_word(Word::new("word1")), id1);
+
+    enc.remove_word("word3");
+    assert_eq!(enc.word_id("word3"), None);
+    assert_eq!(enc.get_word_by_id(id2.unwrap()), None);
+
+    // removing a word removes it's aliases
+    enc.add_word(Word::new("word4"));
+    enc.rename_word("word4", "word5", true).unwrap();
+    assert_eq!(enc.resolve_name("word4"), Some("word5"));
+    enc.remove_word("word5");
+    assert_eq!(enc.resolve_name("word4"), None);
+    enc.add_word(Word::new("word5"));
+    assert_eq!(enc.get_word("word4"), None);
+
+    // a word can be removed with it's alias
+    enc.rename_word("word5", "word6", true).unwrap();
+    enc.remove_word("word5");
+    assert_eq!(enc.get_word("word6"), None);
+    assert_eq!(enc.resolve_name("word5"), None);
+
+    assert_eq!(WordId::from_index(id1.index()), Some(id1));
+    assert_eq!(WordId::from_index(u32::max_value() as usize + 1), None);
+}
+
+#[test]
+fn interned_strings() {
+    let mut enc = Encyclopedia::new();
+    for i in 0..10 {
+        enc.add_word(Word::new_from_collection(format!("goblin{}", i),
+                                               vec![LingTag::Type("creature".to_owned()),
+                                                    LingTag::CustomStr("hp".to_owned(),
+                                                                       Some("10".to_owned()))]));
+    }
+    // the ten names and the three shared strings
+    assert_eq!(enc.interned_amount(), 13);
+    assert!(enc.get_word("goblin3").unwrap().has_tag(&LingTag::Type("creature".to_owned())));
+
+    // removing a tag with a string that was never stored does nothing
+    assert!(!enc.remove_word_tag("goblin3", &LingTag::Custom("unknown".to_owned())).unwrap());
+    assert_eq!(enc.interned_amount(), 13);
+
+    // the tag group names share the strings too, and looking up a group interns nothing
+    enc.add_tag_group("creature", vec![LingTag::Custom("hp".to_owned())]);
+    assert_eq!(enc.interned_amount(), 13);
+    assert!(enc.get_tag_group("monster").is_none());
+    enc.remove_tag_group("monster").unwrap();
+    assert!(enc.rename_tag_group("monster", "beast").is_err());
+    assert_eq!(enc.interned_amount(), 13);
+    assert_eq!(enc.iter_tag_groups().map(|g| g.0).collect::<Vec<_>>(), vec!["creature"]);
+}
+
+#[test]
+fn user_tags() {
+    #[derive(PartialEq, Eq, Hash, Clone, Debug)]
+    enum MonsterTag {
+        Hp(u32),
+        Flying,
+    }
+
+    let mut enc: GenericEncyclopedia<MonsterTag> = GenericEncyclopedia::default();
+    enc.add_word(GenericWord::new_from_collection("bat",
+                                                  vec![LingTag::User(MonsterTag::Hp(2)),
+                                                       LingTag::User(MonsterTag::Flying),
+                                                       LingTag::Type("creature".to_owned())]));
+    enc.add_word_tag("bat", LingTag::Remove(Box::new(LingTag::User(MonsterTag::Hp(5))))).unwrap();
+    enc.rename_word("bat", "giant_bat", false).unwrap();
+
+    let bat = enc.get_word("giant_bat").unwrap();
+    assert_eq!(bat.tag_amount(), 4);
+
+    let mut hp = 0;
+    for tag in bat.iter() {
+        match *tag {
+            LingTag::User(MonsterTag::Hp(v)) => hp += v,
+            LingTag::User(MonsterTag::Flying) => (),
+            _ => (),
+        }
+    }
+    assert_eq!(hp, 2);
+}
+
+#[test]
+fn schema_validation() {
+    let mut schema = TagSchema::new();
+    schema.declare("hp", KeySchema::new(ValueType::Int).required_for("creature")).unwrap();
+    schema.declare("speed", KeySchema::new(ValueType::Float).with_default(TagValue::Float(1.0)))
+        .unwrap();
+    schema.declare("flying", KeySchema::new(ValueType::Flag)).unwrap();
+    assert!(schema.declare("bad", KeySchema::new(ValueType::Int).with_default(TagValue::Bool(true)))
+        .is_err());
+
+    let mut enc = Encyclopedia::new();
+    let creature = |name: &str, tags: Vec<LingTag>| {
+        let mut tags = tags;
+        tags.push(LingTag::Type("creature".to_owned()));
+        Word::new_from_collection(name.to_owned(), tags)
+    };
+    enc.add_word(creature("bat", vec![LingTag::Custom("flying".to_owned())]));
+    enc.add_word(creature("goblin", vec![LingTag::Value("hp".to_owned(), TagValue::Int(5))]));
+    enc.add_word(Word::new_from_collection("rock", vec![LingTag::Custom("helth".to_owned())]));
+    enc.set_schema(schema);
+
+    let errors = enc.validate();
+    assert_eq!(errors.len(), 2);
+    match errors[0] {
+        (ref name, LingError::MissingTagKey(ref key)) if name == "bat" && key == "hp" => (),
+        ref other => panic!("unexpected error {:?}", other),
+    }
+    match errors[1] {
+        (ref name, LingError::UnknownTagKey(ref key)) if name == "rock" && key == "helth" => (),
+        ref other => panic!("unexpected error {:?}", other),
+    }
+
+    // integers are accepted as floats, but not the other way around
+    assert!(enc.add_word_tag("goblin", LingTag::Value("speed".to_owned(), TagValue::Int(2)))
+        .is_ok());
+    match enc.add_word_tag("goblin", LingTag::Value("hp".to_owned(), TagValue::Float(2.5))) {
+        Err(LingError::WrongValueType(ref key, "int")) if key == "hp" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut bat = enc.get_word("bat").unwrap().into_owned();
+    enc.get_schema().unwrap().apply_defaults(&mut bat);
+    assert_eq!(bat.get_float("speed").unwrap(), Some(1.0));
+
+    // the checked words are given the default values
+    match enc.add_checked_word(creature("elf", vec![])) {
+        Err(LingError::MissingTagKey(ref key)) if key == "hp" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(enc.get_word("elf").is_none());
+    enc.add_checked_word(creature("elf", vec![LingTag::Value("hp".to_owned(), TagValue::Int(3))]))
+        .unwrap();
+    assert_eq!(enc.get_word("elf").unwrap().get_float("speed").unwrap(), Some(1.0));
+    enc.begin_transaction().add_word(creature("orc", vec![])).unwrap_err();
+    assert!(enc.get_word("orc").is_none());
+}
+
+#[test]
+fn keyed_tags() {
+    let mut word: Word = Word::new_from_collection("goblin",
+                                                   vec![LingTag::CustomStr("hp".to_owned(),
+                                                                           Some("5".to_owned())),
+                                                        LingTag::Custom("hostile".to_owned()),
+                                                        LingTag::CustomVec("drops".to_owned(),
+                                                                           vec!["bone".to_owned(),
+                                                                                "club".to_owned()]),
+                                                        LingTag::CustomStr("hp".to_owned(),
+                                                                           Some("10".to_owned()))]);
+    // the later tag with the same key wins
+    assert_eq!(word.tag_amount(), 3);
+    assert_eq!(word.get_custom_str("hp"), Some("10"));
+    assert_eq!(word.get_custom_values("drops"), Some(vec!["bone", "club"]));
+    assert_eq!(word.get_custom_values("hostile"), Some(vec![]));
+    assert!(word.has_key("hostile"));
+    assert!(!word.has_key("mana"));
+
+    let mut keys: Vec<&str> = word.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["drops", "hostile", "hp"]);
+
+    word.add_tag(LingTag::Value("hp".to_owned(), TagValue::Int(12)).into());
+    assert_eq!(word.get_custom_str("hp"), None);
+    assert_eq!(word.get_int("hp").unwrap(), Some(12));
+    assert!(word.remove_key("hp"));
+    assert!(!word.has_key("hp"));
+
+    let mut enc = Encyclopedia::new();
+    enc.add_word(word);
+    enc.enable_change_log();
+    assert!(enc.add_word_tag("goblin", LingTag::Custom("drops".to_owned())).unwrap());
+    assert_eq!(enc.get_word("goblin").unwrap().get_custom_values("drops"), Some(vec![]));
+    assert_eq!(enc.drain_changes().count(), 2);
+
+    // the tag groups keep the later tag with the same key too
+    let hp = |v: i64| LingTag::Value("hp".to_owned(), TagValue::Int(v));
+    enc.add_tag_group("group", vec![hp(1), hp(2)]);
+    assert_eq!(enc.get_tag_group("group").unwrap().len(), 1);
+    enc.add_word_tag("goblin", LingTag::UseTagGroup("group".to_owned())).unwrap();
+    assert_eq!(enc.get_effective_word("goblin").unwrap().get_int("hp").unwrap(), Some(2));
+    {
+        let mut tx = enc.begin_transaction();
+        tx.add_tag_group("group", vec![hp(3), hp(1)]).unwrap();
+        tx.commit().unwrap();
+    }
+    assert_eq!(enc.get_effective_word("goblin").unwrap().get_int("hp").unwrap(), Some(1));
+}
+
+#[test]
+fn effective_tags() {
+    let custom = |s: &str| LingTag::Custom(s.to_owned());
+    let hp = |v: i64| LingTag::Value("hp".to_owned(), TagValue::Int(v));
+
+    let mut enc = Encyclopedia::new();
+    enc.add_tag_group("wooden", vec![LingTag::Remove(Box::new(custom("metal"))), custom("burns")]);
+    enc.add_word(Word::new_from_collection("item", vec![custom("metal"), hp(10)]));
+    enc.add_word(Word::new_from_collection("sword",
+                                           vec![LingTag::Parent("item".to_owned()),
+                                                custom("sharp")]));
+    enc.add_word(Word::new_from_collection("wooden_sword",
+                                           vec![LingTag::Parent("sword".to_owned()),
+                                                LingTag::UseTagGroup("wooden".to_owned()),
+                                                hp(5)]));
+
+    let tags = enc.effective_tags("wooden_sword").unwrap();
+    assert!(tags.contains(&custom("sharp")));
+    assert!(tags.contains(&custom("burns")));
+    assert!(tags.contains(&hp(5)));
+    assert!(!tags.contains(&custom("metal")));
+    assert!(!tags.contains(&hp(10)));
+    // the parent's structural tags aren't inherited
+    assert!(!tags.contains(&LingTag::Parent("item".to_owned())));
+    assert!(tags.contains(&LingTag::Parent("sword".to_owned())));
+
+    let resolved = enc.resolve_tags("wooden_sword").unwrap();
+    let metal = resolved.iter().find(|r| r.tag == custom("metal")).unwrap();
+    assert_eq!(metal.source, TagSource::Word("item".to_owned()));
+    assert_eq!(metal.removed_by, Some(TagSource::TagGroup("wooden".to_owned())));
+    let old_hp = resolved.iter().find(|r| r.tag == hp(10)).unwrap();
+    assert_eq!(old_hp.removed_by, Some(TagSource::Word("wooden_sword".to_owned())));
+
+    enc.add_word_tag("item", LingTag::Parent("wooden_sword".to_owned())).unwrap();
+    match enc.effective_tags("sword") {
+        Err(LingError::ParentCycle(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // every level has two parents sharing the ancestors, which are resolved only once
+    let mut enc = Encyclopedia::new();
+    enc.add_word(Word::new_from_collection("a0", vec![hp(0)]));
+    enc.add_word(Word::new_from_collection("b0", vec![custom("b")]));
+    for i in 1..40 {
+        for name in &["a", "b"] {
+            let parents = vec![LingTag::Parent(format!("a{}", i - 1)),
+                               LingTag::Parent(format!("b{}", i - 1))];
+            enc.add_word(Word::new_from_collection(format!("{}{}", name, i), parents));
+        }
+    }
+    let tags = enc.effective_tags("a39").unwrap();
+    assert!(tags.contains(&hp(0)) && tags.contains(&custom("b")));
+}
+
+#[test]
+fn tag_provenance() {
+    use std::path::Path;
+    use super::format::source::{parse_source_words, record_provenance};
+    let custom = |s: &str| LingTag::Custom(s.to_owned());
+
+    let mut enc = Encyclopedia::new();
+    enc.enable_provenance();
+    let src = "item:\n    Custom metal\nsword:\n    Parent item\n    Custom sharp\n";
+    let words = parse_source_words(src).unwrap();
+    for word in &words {
+        enc.add_word(word.word.clone());
+    }
+    record_provenance(&mut enc, Path::new("base.txt"), &words);
+    enc.set_layer(Some("mod".to_owned()));
+    enc.add_word(Word::new_from_collection("rusty", vec![custom("rust")]));
+
+    let prov = enc.get_tag_provenance("item", &custom("metal")).unwrap();
+    assert_eq!(prov.file, Some(Path::new("base.txt").to_owned()));
+    assert_eq!(prov.line, Some(2));
+    assert_eq!(prov.layer, None);
+
+    let explained = enc.explain_tags("sword", &["rusty"]).unwrap();
+    let metal = explained.iter().find(|r| r.tag == custom("metal")).unwrap();
+    assert_eq!(metal.path, vec!["sword".to_owned(), "item".to_owned()]);
+    assert_eq!(metal.provenance.as_ref().and_then(|p| p.line), Some(2));
+    let rust = explained.iter().find(|r| r.tag == custom("rust")).unwrap();
+    assert_eq!(rust.source, TagSource::Modifier("rusty".to_owned()));
+    assert_eq!(rust.provenance.as_ref().and_then(|p| p.layer.clone()),
+               Some("mod".to_owned()));
+}
+
+#[test]
+fn word_graph() {
+    let parent = |s: &str| LingTag::Parent(s.to_owned());
+
+    let mut enc = Encyclopedia::new();
+    enc.add_word(Word::new_from_collection("item", Vec::<LingTag>::new()));
+    enc.add_word(Word::new_from_collection("weapon", vec![parent("item")]));
+    enc.add_word(Word::new_from_collection("tool", vec![parent("item")]));
+    enc.add_word(Word::new_from_collection("sword", vec![parent("weapon")]));
+    enc.add_word(Word::new_from_collection("axe", vec![parent("weapon"), parent("tool")]));
+
+    assert_eq!(enc.parents("axe").unwrap(), vec!["tool", "weapon"]);
+    assert_eq!(enc.children("weapon").unwrap(), vec!["axe", "sword"]);
+    assert_eq!(enc.ancestors("axe").unwrap(), vec!["tool", "weapon", "item"]);
+    assert_eq!(enc.descendants("item").unwrap(), vec!["tool", "weapon", "axe", "sword"]);
+    assert!(enc.is_a("axe", "item"));
+    assert!(enc.is_a("sword", "sword"));
+    assert!(!enc.is_a("item", "sword"));
+    assert_eq!(enc.lowest_common_ancestor("axe", "sword").unwrap(), Some("weapon".to_owned()));
+    assert_eq!(enc.lowest_common_ancestor("axe", "weapon").unwrap(), Some("weapon".to_owned()));
+    assert_eq!(enc.topological_order().unwrap(),
+               vec!["item", "tool", "weapon", "axe", "sword"]);
+    enc.add_word(Word::new_from_collection("knife", vec![parent("tool"), parent("weapon")]));
+    // `tool` and `weapon` are equally close, and `item` is an ancestor of both
+    assert_eq!(enc.lowest_common_ancestor("axe", "knife").unwrap(), Some("tool".to_owned()));
+    enc.remove_word("knife");
+
+    enc.add_word_tag("item", parent("sword")).unwrap();
+    assert!(enc.topological_order().is_err());
+    assert_eq!(enc.ancestors("sword").unwrap(), vec!["weapon", "item"]);
+}
+
+#[test]
+fn word_relations() {
+    let rel = |r: &str, w: &str| LingTag::Relation(r.to_owned(), w.to_owned());
+
+    let mut enc = Encyclopedia::new();
+    enc.declare_inverse_relations("antonym", "antonym");
+    enc.declare_inverse_relations("part_of", "has_part");
+    enc.declare_relation("hyponym");
+    assert_eq!(enc.get_inverse_relation("has_part"), Some("part_of"));
+    assert_eq!(enc.get_inverse_relation("hyponym"), None);
+
+    enc.add_word(Word::new_from_collection("hot", vec![rel("antonym", "cold")]));
+    enc.add_word(Word::new_from_collection("cold", Vec::<LingTag>::new()));
+    enc.add_word(Word::new_from_collection("wheel", vec![rel("part_of", "car")]));
+    enc.add_word(Word::new_from_collection("door", vec![rel("part_of", "car")]));
+    enc.add_word(Word::new_from_collection("car", vec![rel("hyponym", "vehicle")]));
+
+    assert!(enc.has_relation("cold", "antonym", "hot"));
+    assert_eq!(enc.related("car", "has_part").unwrap(), vec!["door", "wheel"]);
+    assert_eq!(enc.relations_of("wheel").unwrap(),
+               vec![("part_of".to_owned(), "car".to_owned())]);
+    assert_eq!(enc.relation_pairs("hyponym"),
+               vec![("car".to_owned(), "vehicle".to_owned())]);
+    assert!(enc.related("vehicle", "hyponym").is_err());
+
+    enc.rename_word("car", "automobile", false).unwrap();
+    assert_eq!(enc.related("wheel", "part_of").unwrap(), vec!["automobile"]);
+    assert_eq!(enc.related("automobile", "has_part").unwrap(), vec!["door", "wheel"]);
+
+    // the inverse relations follow the changes of the words and the relations to the aliases
+    enc.rename_word("automobile", "auto", true).unwrap();
+    enc.add_word(Word::new_from_collection("seat", vec![rel("part_of", "automobile")]));
+    assert_eq!(enc.related("auto", "has_part").unwrap(), vec!["door", "seat", "wheel"]);
+    enc.remove_word_tag("door", &rel("part_of", "auto")).unwrap();
+    enc.remove_word("wheel");
+    assert_eq!(enc.related("auto", "has_part").unwrap(), vec!["seat"]);
+    assert!(!enc.has_relation("auto", "has_part", "door"));
+
+    match enc.check_word(&Word::new_from_collection("x", vec![rel("meronym", "y")])) {
+        Err(LingError::UnknownRelation(ref r)) if r == "meronym" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn translation_coverage() {
+    use std::borrow::Cow;
+    use super::ling::grammeme::Grammeme;
+
+    let text = |k: &str, v: &str| LingTag::CustomStr(k.to_owned(), Some(v.to_owned()));
+    let plural = Grammeme::new(Cow::from("number"), Cow::from("plural"));
+    let genitive = Grammeme::new(Cow::from("case"), Cow::from("gen"));
+
+    let mut enc = Encyclopedia::new();
+    enc.add_gramm_cat(GrammCategory::new("number", Some("singular"), vec!["plural"]));
+    enc.add_gramm_cat(GrammCategory::new("case", Some("nom"), vec!["gen"]));
+    enc.add_required_form("noun", &[plural.clone()]).unwrap();
+    enc.add_required_form("noun", &[plural.clone(), genitive.clone()]).unwrap();
+    let dual = Grammeme::new(Cow::from("number"), Cow::from("dual"));
+    assert!(enc.add_required_form("noun", &[dual]).is_err());
+    assert_eq!(enc.get_required_forms("noun").len(), 2);
+
+    enc.add_word(Word::new_from_collection("goblin",
+                                           vec![LingTag::Type("noun".to_owned()),
+                                                text("fi", "peikko"),
+                                                text("fi|number=plural", "peikot"),
+                                                text("fi|case=gen,number=plural", "peikkojen")]));
+    enc.add_word(Word::new_from_collection("orc",
+                                           vec![LingTag::Type("noun".to_owned()),
+                                                text("sv", "ork")]));
+    enc.add_word(Word::new_from_collection("run", vec![text("fi", "juosta")]));
+    enc.add_tag_group("monster", vec![text("en", "Monster"), text("fi", "Hirviö")]);
+    enc.add_tag_group("internal", vec![LingTag::Custom("hidden".to_owned())]);
+    enc.add_tag_group("stats", vec![text("hp", "10")]);
+    assert_eq!(enc.coverage(&["sv"]).locales[0].total, 7);
+    enc.set_source_locale(Some("en"));
+
+    let report = enc.coverage(&["fi", "sv"]);
+    let fi = report.get("fi").unwrap();
+    assert_eq!(fi.missing_names, vec!["orc"]);
+    assert_eq!(fi.missing_forms,
+               vec![MissingForm {
+                        word: "orc".to_owned(),
+                        grammemes: vec![plural.clone()],
+                    },
+                    MissingForm {
+                        word: "orc".to_owned(),
+                        grammemes: vec![plural, genitive],
+                    }]);
+    assert!(fi.untranslated_groups.is_empty());
+    assert_eq!((fi.translated, fi.total), (5, 8));
+
+    let sv = report.get("sv").unwrap();
+    assert_eq!(sv.missing_names, vec!["goblin", "run"]);
+    assert_eq!(sv.missing_forms.len(), 4);
+    assert_eq!(sv.untranslated_groups, vec!["monster"]);
+    assert_eq!(sv.percentage(), 12.5);
+    assert_eq!(report.percentage(), 37.5);
+    assert_eq!(enc.coverage(&[] as &[&str]).percentage(), 100.0);
+}
+
+#[test]
+fn pseudo_locale() {
+    use std::borrow::Cow;
+    use super::ling::affix::{AffixClass, AffixKind, AffixRule};
+    use super::ling::grammeme::Grammeme;
+
+    assert_eq!(pseudo_localize("Sword"), "[Ŝŵöŕð~~]");
+    assert_eq!(pseudo_localize("a b"), "[å ƀ~]");
+    assert_eq!(pseudo_localize(""), "[]");
+
+    let mut enc = Encyclopedia::new();
+    enc.add_gramm_cat(GrammCategory::new("number", Some("singular"), vec!["plural"]));
+    let rule = AffixRule::new("", "s", ".").unwrap().with_grammeme("number", "plural");
+    enc.add_affix_class(AffixClass::new("S", AffixKind::Suffix).with_rule(rule)).unwrap();
+    let affixes = LingTag::CustomSet("affixes".to_owned(),
+                                     vec!["S".to_owned()].into_iter().collect());
+    enc.add_word(Word::new_from_collection("sword",
+                                           vec![affixes,
+                                                LingTag::CustomStr("fi".to_owned(),
+                                                                   Some("miekka".to_owned()))]));
+    let plural = Grammeme::new(Cow::from("number"), Cow::from("plural"));
+    let dual = Grammeme::new(Cow::from("number"), Cow::from("dual"));
+
+    assert_eq!(enc.render_name("sword", Some("fi")).unwrap(), "miekka");
+    assert_eq!(enc.render_form("sword", Some("fi"), &[plural.clone()]).unwrap(), "swords");
+    assert!(enc.render_form("sword", None, &[dual]).is_err());
+
+    enc.set_pseudo_locale(true);
+    assert_eq!(enc.render_name("sword", None).unwrap(), "[šŵöŕð~~]");
+    assert_eq!(enc.render_name("sword", Some("fi")).unwrap(), "[ɱîéķķå~~]");
+    assert_eq!(enc.render_form("sword", None, &[plural]).unwrap(), "[šŵöŕðš~~]");
+}
+
+#[test]
+fn ordered_iteration() {
+    use super::ling::collation::{Collation, SortOrder};
+
+    let mut enc = Encyclopedia::new();
+    // the relation gives `ångström` an id before it's added
+    enc.declare_relation("see");
+    for name in &["zebra", "Äiti", "apple", "orange"] {
+        let tags = vec![LingTag::Relation("see".to_owned(), "ångström".to_owned())];
+        enc.add_word(Word::new_from_collection(*name, tags));
+    }
+    enc.add_word(Word::new("ångström"));
+    enc.add_word(Word::new("Élan"));
+    enc.add_word(Word::new("zebra"));
+
+    let names = |order: SortOrder| -> Vec<String> {
+        enc.iter_words_ordered(&order).map(|w| w.get_name().into_owned()).collect()
+    };
+    assert_eq!(names(SortOrder::Insertion),
+               vec!["zebra", "Äiti", "apple", "orange", "ångström", "Élan"]);
+    let words: Vec<String> = enc.iter_words().map(|w| w.get_name().into_owned()).collect();
+    assert_eq!(words, names(SortOrder::Insertion));
+    assert_eq!(names(SortOrder::CodePoint),
+               vec!["apple", "orange", "zebra", "Äiti", "Élan", "ångström"]);
+    assert_eq!(names(SortOrder::Collated(Collation::new())),
+               vec!["Äiti", "ångström", "apple", "Élan", "orange", "zebra"]);
+    assert_eq!(names(SortOrder::Collated(Collation::for_locale("fi-FI"))),
+               vec!["apple", "Élan", "orange", "zebra", "ångström", "Äiti"]);
+
+    let collation = Collation::new();
+    let mut list = vec!["b", "B", "a", "á", "A"];
+    list.sort_by(|a, b| collation.compare(a, b));
+    assert_eq!(list, vec!["a", "A", "á", "b", "B"]);
+    list.reverse();
+    list.sort_by_key(|s| collation.sort_key(s));
+    assert_eq!(list, vec!["a", "A", "á", "b", "B"]);
+
+    enc.add_tag_group("monster", vec![]);
+    enc.add_tag_group("creature", vec![]);
+    enc.add_tag_group("animal", vec![]);
+    enc.add_tag_group("monster", vec![LingTag::Custom("big".to_owned())]);
+    enc.rename_tag_group("creature", "beast").unwrap();
+    let groups = |order: SortOrder| -> Vec<&str> {
+        enc.iter_tag_groups_ordered(&order).map(|g| g.0).collect()
+    };
+    assert_eq!(groups(SortOrder::Insertion), vec!["monster", "beast", "animal"]);
+    assert_eq!(groups(SortOrder::CodePoint), vec!["animal", "beast", "monster"]);
+
+    enc.add_gramm_cat(GrammCategory::new("tense", Some("present"), vec!["past"]));
+    enc.add_gramm_cat(GrammCategory::new("case", Some("nom"), vec!["gen"]));
+    enc.add_gramm_cat(GrammCategory::new("number", Some("singular"), vec!["plural"]));
+    enc.add_gramm_cat(GrammCategory::new("tense", Some("present"), vec!["future"]));
+    let cats: Vec<String> = enc.iter_gramm_cats().map(|c| c.get_name().into_owned()).collect();
+    assert_eq!(cats, vec!["tense", "case", "number"]);
+    let cats: Vec<String> = enc.iter_gramm_cats_ordered(&SortOrder::CodePoint)
+        .map(|c| c.get_name().into_owned())
+        .collect();
+    assert_eq!(cats, vec!["case", "number", "tense"]);
+
+    // a removed word that is added again is placed last
+    enc.remove_word("apple");
+    enc.add_word(Word::new("apple"));
+    enc.add_word(Word::new("Äiti"));
+    let words: Vec<String> = enc.iter_words().map(|w| w.get_name().into_owned()).collect();
+    assert_eq!(words, vec!["zebra", "Äiti", "orange", "ångström", "Élan", "apple"]);
+}
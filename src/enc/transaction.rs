@@ -0,0 +1,300 @@
+//! Contains the `Transaction` struct for grouping encyclopedia edits that can be undone.
+use std::collections::HashSet;
+use std::mem;
+
+use enc::encyclopedia::{GenericEncyclopedia, SavedEntry};
+use enc::word::GenericWord;
+use enc::ling::{LingTag, UserTag, NoUserTag};
+use enc::ling::grammeme::GrammCategory;
+use enc::ling::error::LingError;
+
+type LingResult<T> = Result<T, LingError>;
+
+/// A single change to an encyclopedia that sets an entry to the given value or removes it if the
+/// value is `None`.
+#[derive(Clone, Debug)]
+enum Edit<U> {
+    Word(String, Option<HashSet<LingTag<U>>>),
+    TagGroup(String, Option<HashSet<LingTag<U>>>),
+    GrammCat(String, Option<(Option<String>, HashSet<String>)>),
+}
+
+impl<U: UserTag> Edit<U> {
+    /// Saves the state of the entry the edit changes.
+    fn save(&self, enc: &GenericEncyclopedia<U>) -> SavedEntry<U> {
+        match *self {
+            Edit::Word(ref name, _) => enc.save_word(name),
+            Edit::TagGroup(ref name, _) => enc.save_tag_group(name),
+            Edit::GrammCat(ref name, _) => enc.save_gramm_cat(name),
+        }
+    }
+
+    /// Applies the edit to the encyclopedia and returns the state of the edited entry after it.
+    fn apply(self, enc: &mut GenericEncyclopedia<U>) -> LingResult<SavedEntry<U>> {
+        match self {
+            Edit::Word(name, tags) => {
+                match tags {
+                    Some(tags) => {
+                        enc.add_word(GenericWord::new_from_collection(name.clone(), tags));
+                    }
+                    None => {
+                        // aliases are ignored, since the edit is about the word with exactly
+                        // this name
+                        let exact = enc.get_word(&name as &str)
+                            .map_or(false, |w| w.get_name() == name);
+                        if exact {
+                            enc.remove_word(&name as &str);
+                        }
+                    }
+                }
+                Ok(enc.save_word(&name))
+            }
+            Edit::TagGroup(name, tags) => {
+                match tags {
+                    Some(tags) => enc.add_tag_group(name.clone(), tags),
+                    None => try!(enc.remove_tag_group(&name)),
+                }
+                Ok(enc.save_tag_group(&name))
+            }
+            Edit::GrammCat(name, values) => {
+                match values {
+                    Some((def, values)) => {
+                        enc.add_gramm_cat(GrammCategory::new(name.clone(), def, values))
+                    }
+                    None => enc.remove_gramm_cat(&name),
+                }
+                Ok(enc.save_gramm_cat(&name))
+            }
+        }
+    }
+}
+
+/// The states of an edited entry before and after an edit.
+type Step<U> = (SavedEntry<U>, SavedEntry<U>);
+
+/// The undo and redo stacks of an encyclopedia.
+///
+/// Every entry is the list of the steps of a transaction. Undoing restores the states before
+/// the steps in reverse order and redoing restores the states after them, so the edited entries
+/// get back their revisions, provenance, aliases and places in the insertion order.
+pub(crate) struct History<U> {
+    undo: Vec<Vec<Step<U>>>,
+    redo: Vec<Vec<Step<U>>>,
+}
+
+impl<U> History<U> {
+    pub fn new() -> Self {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum TransactionState {
+    Open,
+    Committed,
+    Aborted,
+}
+
+/// A group of encyclopedia edits that can be committed or rolled back together.
+///
+/// The edits are applied to the encyclopedia immediately, but they are only recorded into the
+/// undo history when the transaction is committed.
+/// If any edit fails validation, the whole transaction is rolled back immediately and every
+/// following operation fails with `LingError::TransactionAborted`, so a failed transaction never
+/// leaves the encyclopedia partially edited.
+/// A transaction that is dropped without committing is rolled back.
+///
+/// Rolling back restores the edited words, tag groups and categories exactly as they were, with
+/// their revisions and provenance, and removes the changes of the transaction from the change
+/// log, so a rolled back transaction looks like it never happened.
+///
+/// Created with `Encyclopedia::begin_transaction`.
+pub struct Transaction<'a, U: UserTag = NoUserTag> {
+    enc: &'a mut GenericEncyclopedia<U>,
+    /// The states of the edited entries before and after each edit.
+    steps: Vec<Step<U>>,
+    /// The length of the change log when the transaction began.
+    log_len: Option<usize>,
+    state: TransactionState,
+}
+
+impl<'a, U: UserTag> Transaction<'a, U> {
+    /// Adds a word, replacing any previous word with the same name.
+    /// The word is completed and checked with `Encyclopedia::apply_schema` first.
+    pub fn add_word(&mut self, word: GenericWord<U>) -> LingResult<()> {
+        try!(self.check_open());
+        let mut word = word;
+        if let Err(e) = self.enc.apply_schema(&mut word) {
+            self.abort();
+            return Err(e);
+        }
+        let (name, tags) = word.into_map_entry();
+        self.apply(Edit::Word(name, Some(tags)))
+    }
+
+    /// Removes the word with the given name.
+    pub fn remove_word<T: Into<String>>(&mut self, name: T) -> LingResult<()> {
+        try!(self.check_open());
+        self.apply(Edit::Word(name.into(), None))
+    }
+
+    /// Adds a tag group, replacing any previous group with the same name.
+    /// The tags are checked with `Encyclopedia::check_tags` first.
+    pub fn add_tag_group<T, I>(&mut self, name: T, tags: I) -> LingResult<()>
+        where T: Into<String>,
+              I: IntoIterator<Item = LingTag<U>>
+    {
+        try!(self.check_open());
+        // the later tags with the same keys win before the order is lost
+        let tags = GenericWord::new_from_collection("", tags).into_map_entry().1;
+        if let Err(e) = self.enc.check_tags(tags.iter().map(Into::into)) {
+            self.abort();
+            return Err(e);
+        }
+        self.apply(Edit::TagGroup(name.into(), Some(tags)))
+    }
+
+    /// Removes the tag group with the given name.
+    ///
+    /// Fails like `Encyclopedia::remove_tag_group`.
+    pub fn remove_tag_group<T: Into<String>>(&mut self, name: T) -> LingResult<()> {
+        try!(self.check_open());
+        self.apply(Edit::TagGroup(name.into(), None))
+    }
+
+    /// Adds a grammatical category, replacing any previous category with the same name.
+    pub fn add_gramm_cat(&mut self, categ: GrammCategory) -> LingResult<()> {
+        try!(self.check_open());
+        let (name, values) = categ.into_map_entry();
+        self.apply(Edit::GrammCat(name, Some(values)))
+    }
+
+    /// Removes the grammatical category with the given name.
+    pub fn remove_gramm_cat<T: Into<String>>(&mut self, name: T) -> LingResult<()> {
+        try!(self.check_open());
+        self.apply(Edit::GrammCat(name.into(), None))
+    }
+
+    /// Commits the transaction, so it can be undone with `Encyclopedia::undo`.
+    ///
+    /// Committing clears the redo history of the encyclopedia.
+    /// Fails if the transaction has been aborted.
+    pub fn commit(mut self) -> LingResult<()> {
+        try!(self.check_open());
+        self.state = TransactionState::Committed;
+
+        let steps = mem::replace(&mut self.steps, Vec::new());
+        if !steps.is_empty() {
+            self.enc.history.undo.push(steps);
+            self.enc.history.redo.clear();
+        }
+        Ok(())
+    }
+
+    /// Reverts all of the edits made in this transaction.
+    pub fn rollback(mut self) {
+        self.abort();
+    }
+
+    /// Returns an error if the transaction isn't open anymore.
+    fn check_open(&self) -> LingResult<()> {
+        match self.state {
+            TransactionState::Open => Ok(()),
+            _ => Err(LingError::TransactionAborted),
+        }
+    }
+
+    /// Applies the edit and records it's step, or aborts the transaction if the edit fails.
+    fn apply(&mut self, edit: Edit<U>) -> LingResult<()> {
+        let before = edit.save(self.enc);
+        match edit.apply(self.enc) {
+            Ok(after) => {
+                self.steps.push((before, after));
+                Ok(())
+            }
+            Err(e) => {
+                self.abort();
+                Err(e)
+            }
+        }
+    }
+
+    /// Restores the edited entries and closes the transaction.
+    fn abort(&mut self) {
+        for (before, _) in self.steps.drain(..).rev() {
+            self.enc.restore(before);
+        }
+        if let Some(len) = self.log_len {
+            self.enc.truncate_change_log(len);
+        }
+        self.state = TransactionState::Aborted;
+    }
+}
+
+impl<'a, U: UserTag> Drop for Transaction<'a, U> {
+    fn drop(&mut self) {
+        if self.state == TransactionState::Open {
+            self.abort();
+        }
+    }
+}
+
+impl<U: UserTag> GenericEncyclopedia<U> {
+    /// Starts a new transaction for editing this encyclopedia.
+    pub fn begin_transaction(&mut self) -> Transaction<U> {
+        Transaction {
+            log_len: self.change_log_len(),
+            enc: self,
+            steps: Vec::new(),
+            state: TransactionState::Open,
+        }
+    }
+
+    /// Undoes the last committed transaction that hasn't been undone yet.
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo.pop() {
+            Some(steps) => {
+                for &(ref before, _) in steps.iter().rev() {
+                    self.restore_logged(before.clone());
+                }
+                self.history.redo.push(steps);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the last undone transaction.
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo.pop() {
+            Some(steps) => {
+                for &(_, ref after) in &steps {
+                    self.restore_logged(after.clone());
+                }
+                self.history.undo.push(steps);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if there is a transaction to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    /// Returns true if there is a transaction to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    /// Forgets all of the transactions in the undo and redo history.
+    pub fn clear_history(&mut self) {
+        self.history = History::new();
+    }
+}
@@ -3,34 +3,58 @@ use std::collections::{HashSet, hash_set};
 use std::iter::Iterator;
 use std::borrow::Cow;
 
-use enc::ling::LingTag;
+use enc::ling::{LingTag, TagValue, UserTag, NoUserTag, LingError};
+
+type LingResult<T> = Result<T, LingError>;
+
+/// A word without user-defined tags.
+///
+/// This is what most code uses, and naming it lets the type of the tags be inferred in code
+/// like `Word::new_from_collection("goblin", vec![LingTag::Custom(..)])`.
+pub type Word<'a> = GenericWord<'a, NoUserTag>;
 
 /// A view to a word that has a name and associated tags.
+///
+/// `U` is the type of the user-defined tags of the word, see `LingTag::User`.
 #[derive(PartialEq, Eq, Clone, Debug)]
-pub struct Word<'a> {
+pub struct GenericWord<'a, U: UserTag> {
     name: Cow<'a, str>,
-    tags: HashSet<Cow<'a, LingTag>>,
+    tags: HashSet<Cow<'a, LingTag<U>>>,
 }
 
 /// A word that has a name and a set of tags.
-impl<'a> Word<'a> {
+impl<'a, U: UserTag> GenericWord<'a, U> {
     /// Creates a new empty word.
     pub fn new<T: 'a + Into<Cow<'a, str>>>(name: T) -> Self {
-        Word {
+        GenericWord {
             name: name.into(),
             tags: HashSet::new(),
         }
     }
 
     /// Creates a word from a name and a collection of words.
-    pub fn new_from_collection<T, U, V>(name: T, coll: U) -> Self
+    ///
+    /// The tags are added in order with `add_tag`, so if many custom tags have the same key, the
+    /// last one of them is kept.
+    pub fn new_from_collection<T, I, V>(name: T, coll: I) -> Self
         where T: 'a + Into<Cow<'a, str>>,
-              U: IntoIterator<Item = V>,
-              V: Into<Cow<'a, LingTag>>
+              I: IntoIterator<Item = V>,
+              V: Into<Cow<'a, LingTag<U>>>
     {
-        Word {
+        let mut word = GenericWord::new(name);
+        for tag in coll {
+            word.add_tag(tag.into());
+        }
+        word
+    }
+
+    /// Creates a word from a name and a set of tags that has at most one custom tag per key.
+    pub(crate) fn from_set<T: 'a + Into<Cow<'a, str>>>(name: T,
+                                                       tags: HashSet<Cow<'a, LingTag<U>>>)
+                                                       -> Self {
+        GenericWord {
             name: name.into(),
-            tags: coll.into_iter().map(&Into::into).collect(),
+            tags: tags,
         }
     }
 
@@ -40,20 +64,168 @@ impl<'a> Word<'a> {
     }
 
     // Returns the tags of the word.
-    pub fn get_tags(&self) -> &HashSet<Cow<'a, LingTag>> {
+    pub fn get_tags(&self) -> &HashSet<Cow<'a, LingTag<U>>> {
         &self.tags
     }
 
-    /// Adds the given tag to the word, replacing any previous tag with the same value.
-    pub fn add_tag(&mut self, tag: Cow<'a, LingTag>) {
+    /// Adds the given tag to the word.
+    ///
+    /// A word has at most one custom or value tag with the same key, see `LingTag::key`, so
+    /// such a tag replaces any previous tag with the same key.
+    pub fn add_tag(&mut self, tag: Cow<'a, LingTag<U>>) {
+        if let Some(key) = tag.key() {
+            self.tags.retain(|t| t.key() != Some(key));
+        }
         self.tags.insert(tag);
     }
 
+    /// Removes the given tag from the word.
+    /// Returns true if the word had the tag.
+    pub fn remove_tag(&mut self, tag: &LingTag<U>) -> bool {
+        self.tags.remove(tag)
+    }
+
+    /// Applies the tags of a modifier word to this word.
+    ///
+    /// The `LingTag::Remove` tags of the modifier are applied first, removing the matching tags
+    /// from this word as described in `LingTag::overrides`, and then the rest of the modifier's
+    /// tags are added, replacing the tags of this word with the same keys.
+    /// When applying many modifiers one after another, a later modifier can remove the tags added
+    /// by an earlier one.
+    pub fn apply_modifier(&mut self, modifier: &GenericWord<U>) {
+        for tag in modifier.tags.iter() {
+            if let LingTag::Remove(ref removed) = **tag {
+                self.tags.retain(|t| !removed.overrides(t));
+            }
+        }
+        for tag in modifier.tags.iter() {
+            match **tag {
+                LingTag::Remove(_) => (),
+                ref tag => self.add_tag(Cow::Owned(tag.clone())),
+            }
+        }
+    }
+
     /// Returns true if the word has the given tag.
-    pub fn has_tag(&self, tag: &'a LingTag) -> bool {
+    pub fn has_tag(&self, tag: &'a LingTag<U>) -> bool {
         self.tags.contains(tag)
     }
 
+    /// Returns true if the word has a custom or value tag with the given key.
+    pub fn has_key(&self, key: &str) -> bool {
+        self.get_keyed(key).is_some()
+    }
+
+    /// Returns the custom or value tag with the given key, or `None` if the word has no such
+    /// tag.
+    ///
+    /// Adding a keyed tag replaces the tag with the same key, so there is at most one such tag.
+    pub fn get_keyed(&self, key: &str) -> Option<&LingTag<U>> {
+        self.tags.iter().map(|t| &**t).find(|t| t.key() == Some(key))
+    }
+
+    /// Removes the custom or value tag with the given key.
+    /// Returns true if the word had such a tag.
+    pub fn remove_key(&mut self, key: &str) -> bool {
+        let len = self.tags.len();
+        self.tags.retain(|t| t.key() != Some(key));
+        self.tags.len() != len
+    }
+
+    /// Returns the string value of the `LingTag::CustomStr` tag with the given key.
+    ///
+    /// Returns `None` if the word has no such tag or if the tag has no value.
+    pub fn get_custom_str(&self, key: &str) -> Option<&str> {
+        match self.get_keyed(key) {
+            Some(&LingTag::CustomStr(_, Some(ref v))) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the string values of the custom tag with the given key.
+    ///
+    /// `LingTag::Custom` has no values, `LingTag::CustomStr` has zero or one value and the
+    /// values of `LingTag::CustomVec` and `LingTag::CustomSet` are returned in their order.
+    /// Returns `None` if the word has no custom tag with the key.
+    pub fn get_custom_values(&self, key: &str) -> Option<Vec<&str>> {
+        match self.get_keyed(key) {
+            Some(&LingTag::Custom(_)) => Some(Vec::new()),
+            Some(&LingTag::CustomStr(_, ref v)) => Some(v.iter().map(|s| s as &str).collect()),
+            Some(&LingTag::CustomVec(_, ref v)) => Some(v.iter().map(|s| s as &str).collect()),
+            Some(&LingTag::CustomSet(_, ref v)) => Some(v.iter().map(|s| s as &str).collect()),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the keys of the custom and value tags of the word.
+    pub fn keys(&self) -> KeyIter<U> {
+        KeyIter { iter: self.tags.iter() }
+    }
+
+    /// Returns the value of the `LingTag::Value` tag with the given key, or `None` if the word
+    /// has no such tag.
+    pub fn get_value(&self, key: &str) -> Option<&TagValue> {
+        match self.get_keyed(key) {
+            Some(&LingTag::Value(_, ref value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value with the given key converted by the function `f`.
+    /// Fails with `LingError::WrongValueType` if `f` returns `None`.
+    fn get_typed<'b, T, F>(&'b self,
+                           key: &str,
+                           expected: &'static str,
+                           f: F)
+                           -> LingResult<Option<T>>
+        where F: FnOnce(&'b TagValue) -> Option<T>
+    {
+        match self.get_value(key) {
+            Some(value) => {
+                f(value).map(Some).ok_or(LingError::WrongValueType(key.to_owned(), expected))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the integer value with the given key, see `TagValue::as_int`.
+    ///
+    /// Returns `None` if the word has no value with the key and fails if the value isn't an
+    /// integer. The other typed accessors work the same way.
+    pub fn get_int(&self, key: &str) -> LingResult<Option<i64>> {
+        self.get_typed(key, "int", TagValue::as_int)
+    }
+
+    /// Returns the float value with the given key, see `TagValue::as_float`.
+    pub fn get_float(&self, key: &str) -> LingResult<Option<f64>> {
+        self.get_typed(key, "float", TagValue::as_float)
+    }
+
+    /// Returns the boolean value with the given key.
+    pub fn get_bool(&self, key: &str) -> LingResult<Option<bool>> {
+        self.get_typed(key, "bool", TagValue::as_bool)
+    }
+
+    /// Returns the integer range with the given key.
+    pub fn get_int_range(&self, key: &str) -> LingResult<Option<(i64, i64)>> {
+        self.get_typed(key, "int range", TagValue::as_int_range)
+    }
+
+    /// Returns the float range with the given key, see `TagValue::as_float_range`.
+    pub fn get_float_range(&self, key: &str) -> LingResult<Option<(f64, f64)>> {
+        self.get_typed(key, "float range", TagValue::as_float_range)
+    }
+
+    /// Returns the string value with the given key.
+    pub fn get_str(&self, key: &str) -> LingResult<Option<&str>> {
+        self.get_typed(key, "string", TagValue::as_str)
+    }
+
+    /// Returns the list value with the given key.
+    pub fn get_list(&self, key: &str) -> LingResult<Option<&[TagValue]>> {
+        self.get_typed(key, "list", TagValue::as_list)
+    }
+
     /// Returns the amount of tags stored.
     pub fn tag_amount(&self) -> usize {
         self.tags.len()
@@ -65,24 +237,57 @@ impl<'a> Word<'a> {
     }
 
     /// Returns an iterator over the tags of the word.
-    pub fn iter(&'a self) -> TagIter<'a> {
+    pub fn iter(&'a self) -> TagIter<'a, U> {
         TagIter { iter: self.tags.iter() }
     }
 
+    /// Consumes self and returns a word that owns it's name and tags.
+    pub fn into_owned(self) -> GenericWord<'static, U> {
+        GenericWord {
+            name: Cow::Owned(self.name.into_owned()),
+            tags: self.tags.into_iter().map(|t| Cow::Owned(t.into_owned())).collect(),
+        }
+    }
+
     /// Consumes self and returns a tuple that can be used to store this into a map structure.
-    pub fn into_map_entry(self) -> (String, HashSet<LingTag>) {
+    pub fn into_map_entry(self) -> (String, HashSet<LingTag<U>>) {
         (self.name.into_owned(), self.tags.into_iter().map(&Cow::into_owned).collect())
     }
 }
 
+impl<'a> Word<'a> {
+    /// Returns this word as a word with the user-defined tags of the type `U`, see
+    /// `LingTag::with_user_tag`.
+    pub fn with_user_tag<U: UserTag>(self) -> GenericWord<'static, U> {
+        let (name, tags) = self.into_map_entry();
+        GenericWord::new_from_collection(name, tags.into_iter().map(LingTag::with_user_tag))
+    }
+}
+
 /// An iterator over the tags of a word.
-pub struct TagIter<'a> {
+pub struct TagIter<'a, U: UserTag = NoUserTag> {
     // Please, do not edit this variable outside this module.
-    iter: hash_set::Iter<'a, Cow<'a, LingTag>>,
+    iter: hash_set::Iter<'a, Cow<'a, LingTag<U>>>,
 }
-impl<'a> Iterator for TagIter<'a> {
-    type Item = Cow<'a, LingTag>;
+impl<'a, U: UserTag> Iterator for TagIter<'a, U> {
+    type Item = Cow<'a, LingTag<U>>;
     fn next(&mut self) -> Option<Self::Item> {
         self.iter.next().map(|x| x.clone())
     }
 }
+
+/// An iterator over the keys of the custom and value tags of a word.
+pub struct KeyIter<'a, U: UserTag = NoUserTag> {
+    iter: hash_set::Iter<'a, Cow<'a, LingTag<U>>>,
+}
+impl<'a, U: UserTag> Iterator for KeyIter<'a, U> {
+    type Item = &'a str;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(tag) = self.iter.next() {
+            if let Some(key) = tag.key() {
+                return Some(key);
+            }
+        }
+        None
+    }
+}
@@ -1,63 +1,576 @@
 //! Contains `EncSysWorld` struct and the helper struct for building entities, `EncEntityBuilder`.
 extern crate specs;
 
+mod prefab;
+mod reload;
+
 #[cfg(test)]
 mod test;
 
 use std::ptr;
+use std::rc::Rc;
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 use util::EncSysType;
-use enc::Encyclopedia;
-use enc::Word;
+use enc::GenericEncyclopedia;
+use enc::{GenericWord, WordId};
+use enc::ling::{LingTag, LingError, UserTag, NoUserTag};
+
+pub use self::prefab::*;
+pub use self::reload::*;
 
 /// The master manager for the encyclopedia and entities.
-pub struct EncSysWorld<C: EncSysType> {
+///
+/// `C` is the type of the component names and `U` is the type of the user-defined tags of the
+/// words.
+///
+/// The entities and the prefabs are built from the stored tags of the words, so the tags the
+/// words inherit from their parents and tag groups aren't applied, and changing a parent or a
+/// tag group doesn't make the spawned entities out of date. A word is spawned with it's
+/// effective tags by giving `Encyclopedia::get_effective_word` to `entity_from_word`.
+pub struct EncSysWorld<C: EncSysType, U: UserTag = NoUserTag> {
     /// The encyclopedia that contains words with their associated tags.
-    pub enc: Encyclopedia,
+    pub enc: GenericEncyclopedia<U>,
 
     /// The `specs::World` that contains all of the entities and components.
     ///
     /// Notice that we are using the latest version from the git repo with the support for dynamic
     /// component types.
     pub ecs: specs::World<C>,
+
+    /// The rule function the prefabs are compiled with, see `set_prefab_rule`.
+    prefab_rule: Option<PrefabRule<C, U>>,
+
+    /// The compiled prefabs by their word ids.
+    prefabs: HashMap<WordId, Rc<Prefab<C>>>,
+
+    /// The entities spawned from stored words.
+    spawned: HashMap<specs::Entity, Spawned<C, U>>,
+
+    /// The latest versions of the words the tracked entities were built from, with their
+    /// revisions, by their word ids. The entities built from the same version share it.
+    snapshots: HashMap<WordId, (u64, Rc<GenericWord<'static, U>>)>,
+
+    /// The encyclopedia source files watched for changes.
+    watched: HashMap<PathBuf, WatchedFile>,
+}
+
+/// A rule function that compiles a word into a prefab.
+type PrefabRule<C, U> = Rc<dyn Fn(GenericWord<U>, &mut PrefabBuilder<C>)>;
+
+/// An entity spawned from a stored word, tracked so it can be reconciled when the word changes.
+struct Spawned<C: EncSysType, U: UserTag> {
+    /// The word the entity was spawned from.
+    word: WordId,
+    /// The modifier words applied to the word, see `EncSysWorld::spawn_with`.
+    modifiers: Vec<WordId>,
+    /// The tags applied to the word after the modifiers, see `EncSysWorld::spawn_with_tags`.
+    tags: Vec<LingTag<U>>,
+    /// The revision of the word and the modifiers the components of the entity match.
+    revision: u64,
+    comps: SpawnedComps<C, U>,
+}
+
+/// What the components of a spawned entity match.
+enum SpawnedComps<C: EncSysType, U: UserTag> {
+    /// The components of a prefab.
+    Prefab(Rc<Prefab<C>>),
+    /// The components created by the rule function of `spawn_with` or `entity_from_word` from
+    /// the versions of the word and the modifier words the entity was built from, with the tags
+    /// of the entity applied after them.
+    Words(Vec<Rc<GenericWord<'static, U>>>),
 }
 
 impl<C: EncSysType> EncSysWorld<C> {
     /// Creates a new empty `EncSysWorld`.
+    ///
+    /// The worlds with user-defined tags are created with `EncSysWorld::default`.
     pub fn new() -> Self {
+        EncSysWorld::default()
+    }
+}
+
+impl<C: EncSysType, U: UserTag> Default for EncSysWorld<C, U> {
+    fn default() -> Self {
         EncSysWorld {
-            enc: Encyclopedia::new(),
+            enc: GenericEncyclopedia::default(),
             ecs: specs::World::new_w_comp_id(),
+            prefab_rule: None,
+            prefabs: HashMap::new(),
+            spawned: HashMap::new(),
+            snapshots: HashMap::new(),
+            watched: HashMap::new(),
         }
     }
+}
 
+impl<C: EncSysType, U: UserTag> EncSysWorld<C, U> {
     fn builder(&mut self) -> EncEntityBuilder<C> {
         EncEntityBuilder { builder: self.ecs.create_now() }
     }
+
+    /// Creates and stores an entity from the word by using the rule function `f`.
+    fn build_entity<F>(&mut self, word: GenericWord<U>, f: &F) -> specs::Entity
+        where F: Fn(GenericWord<U>, &mut EncEntityBuilder<C>)
+    {
+        // here is the builder that will construct the entity
+        let mut builder = self.builder();
+        // magic happens here
+        f(word, &mut builder);
+        // return the created entity
+        builder.finish()
+    }
+
+    /// Returns the stored word with the given id after applying the modifier words and the
+    /// tags to it, or `None` if any of the words isn't stored.
+    fn modified_word(&self,
+                     id: WordId,
+                     modifiers: &[WordId],
+                     tags: &[LingTag<U>])
+                     -> Option<GenericWord<'static, U>> {
+        let mut word = match self.enc.get_word_by_id(id) {
+            Some(word) => word.into_owned(),
+            None => return None,
+        };
+        for &modifier in modifiers {
+            match self.enc.get_word_by_id(modifier) {
+                Some(modifier) => word.apply_modifier(&modifier),
+                None => return None,
+            }
+        }
+        if !tags.is_empty() {
+            word.apply_modifier(&GenericWord::new_from_collection("", tags));
+        }
+        Some(word)
+    }
+
+    /// Returns the current version of the stored word with the given id, shared with the other
+    /// entities built from the same version, or `None` if the word isn't stored.
+    fn snapshot(&mut self, id: WordId) -> Option<Rc<GenericWord<'static, U>>> {
+        let revision = match self.enc.word_revision_by_id(id) {
+            Some(revision) => revision,
+            None => return None,
+        };
+        match self.snapshots.get(&id) {
+            Some(&(rev, ref word)) if rev == revision => return Some(word.clone()),
+            _ => (),
+        }
+        let word = Rc::new(self.enc.get_word_by_id(id).unwrap().into_owned());
+        self.snapshots.insert(id, (revision, word.clone()));
+        Some(word)
+    }
+
+    /// Returns the latest revision of the word and the modifier words with the given ids, or
+    /// `None` if any of the words isn't stored.
+    ///
+    /// The revisions only grow, so this changes whenever any of the words changes.
+    fn spawned_revision(&self, id: WordId, modifiers: &[WordId]) -> Option<u64> {
+        let revisions: Option<Vec<u64>> = Some(id)
+            .into_iter()
+            .chain(modifiers.iter().cloned())
+            .map(|id| self.enc.word_revision_by_id(id))
+            .collect();
+        revisions.and_then(|r| r.into_iter().max())
+    }
+
+    /// Creates and stores an entity from the word with the given name, after applying the
+    /// modifier words with the given names to it in order.
+    ///
+    /// For example `spawn_with("sword", &["rusty", "iron"], &f)` creates a rusty iron sword.
+    /// See `WordToEntity::entity_from_word_with` for the precedence of the modifiers.
+    /// The entity is updated when the word or the modifiers change, see `reconcile_word`.
+    pub fn spawn_with<F, T>(&mut self,
+                            name: &str,
+                            modifiers: &[T],
+                            f: &F)
+                            -> Result<specs::Entity, LingError>
+        where F: Fn(GenericWord<U>, &mut EncEntityBuilder<C>),
+              T: AsRef<str>
+    {
+        self.spawn_with_tags(name, modifiers, Vec::new(), f)
+    }
+
+    /// Like `spawn_with`, but applies the extra tags to the word after the modifier words, as
+    /// if they were the tags of one more modifier.
+    ///
+    /// The extra tags can be `LingTag::Remove` tags, so an entity can be spawned without some
+    /// of the tags of it's word without storing a modifier word for it.
+    pub fn spawn_with_tags<F, T, I>(&mut self,
+                                    name: &str,
+                                    modifiers: &[T],
+                                    tags: I,
+                                    f: &F)
+                                    -> Result<specs::Entity, LingError>
+        where F: Fn(GenericWord<U>, &mut EncEntityBuilder<C>),
+              T: AsRef<str>,
+              I: IntoIterator<Item = LingTag<U>>
+    {
+        let word_id = |enc: &GenericEncyclopedia<U>, name: &str| {
+            enc.word_id(name).ok_or(LingError::NoSuchWord(name.to_owned()))
+        };
+        let id = try!(word_id(&self.enc, name));
+        let modifiers = try!(modifiers.iter()
+            .map(|m| word_id(&self.enc, m.as_ref()))
+            .collect::<Result<Vec<WordId>, LingError>>());
+        let tags: Vec<LingTag<U>> = tags.into_iter().collect();
+
+        let words: Vec<Rc<GenericWord<'static, U>>> = Some(id)
+            .into_iter()
+            .chain(modifiers.iter().cloned())
+            .map(|id| self.snapshot(id).unwrap())
+            .collect();
+        let word = apply_modifiers(&words, &tags);
+        let spawned = Spawned {
+            word: id,
+            revision: self.spawned_revision(id, &modifiers).unwrap(),
+            modifiers: modifiers,
+            tags: tags,
+            comps: SpawnedComps::Words(words),
+        };
+        let entity = self.build_entity(word, f);
+        self.spawned.insert(entity, spawned);
+        Ok(entity)
+    }
+
+    /// Returns the id of the word the entity was spawned from.
+    ///
+    /// The id stays the same even if the word is renamed. Returns `None` if the entity wasn't
+    /// spawned from a stored word or if it isn't tracked anymore.
+    pub fn get_spawned_word(&self, entity: specs::Entity) -> Option<WordId> {
+        self.spawned.get(&entity).map(|s| s.word)
+    }
+
+    /// Stops tracking a spawned entity, so it won't be reconciled anymore.
+    ///
+    /// The deleted entities are forgotten when they are reconciled, but calling this when an
+    /// entity is deleted keeps the tracked entities from piling up between the reconciliations.
+    pub fn forget_entity(&mut self, entity: specs::Entity) {
+        self.spawned.remove(&entity);
+    }
+
+    /// Stops tracking the spawned entities that have been deleted from the `specs::World`.
+    ///
+    /// Returns the amount of forgotten entities.
+    pub fn forget_dead_entities(&mut self) -> usize {
+        let len = self.spawned.len();
+        let ecs = &self.ecs;
+        self.spawned.retain(|&entity, _| ecs.is_alive(entity));
+        self.forget_unused_snapshots();
+        len - self.spawned.len()
+    }
+
+    /// Forgets the versions of the words no tracked entity was built from.
+    fn forget_unused_snapshots(&mut self) {
+        self.snapshots.retain(|_, &mut (_, ref word)| Rc::strong_count(word) > 1);
+    }
+}
+
+impl<C: EncSysType + 'static, U: UserTag> EncSysWorld<C, U> {
+    /// Sets the rule function the prefabs are compiled with.
+    ///
+    /// Replacing the rule removes the cached prefabs, so they are compiled again with the new
+    /// rule when they are needed.
+    pub fn set_prefab_rule<F>(&mut self, f: F)
+        where F: Fn(GenericWord<U>, &mut PrefabBuilder<C>) + 'static
+    {
+        self.prefab_rule = Some(Rc::new(f));
+        self.prefabs.clear();
+    }
+
+    /// Compiles the word with the given name into a prefab by using the prefab rule and caches
+    /// it, replacing any previously cached prefab of the word.
+    ///
+    /// Returns false if no such word was found in the encyclopedia or if no prefab rule has
+    /// been set.
+    pub fn compile_prefab(&mut self, name: &str) -> bool {
+        match (self.enc.word_id(name), self.prefab_rule.clone()) {
+            (Some(id), Some(rule)) => {
+                self.compile_prefab_by_id(id, &*rule);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Compiles the prefab of a stored word and returns it.
+    fn compile_prefab_by_id<F>(&mut self, id: WordId, f: &F) -> Rc<Prefab<C>>
+        where F: ?Sized + Fn(GenericWord<U>, &mut PrefabBuilder<C>)
+    {
+        let revision = self.enc.word_revision_by_id(id).unwrap();
+        let prefab = Rc::new(compile(self.enc.get_word_by_id(id).unwrap(), revision, f));
+        self.prefabs.insert(id, prefab.clone());
+        prefab
+    }
+
+    /// Returns the up to date prefab of a stored word, compiling it first if needed.
+    fn prefab_by_id<F>(&mut self, id: WordId, f: &F) -> Rc<Prefab<C>>
+        where F: ?Sized + Fn(GenericWord<U>, &mut PrefabBuilder<C>)
+    {
+        let revision = self.enc.word_revision_by_id(id);
+        match self.prefabs.get(&id) {
+            Some(prefab) if Some(prefab.get_revision()) == revision => return prefab.clone(),
+            _ => (),
+        }
+        self.compile_prefab_by_id(id, f)
+    }
+
+    /// Creates and stores an entity from the cached prefab of the word with the given name and
+    /// returns the created `specs::Entity` value.
+    ///
+    /// If the word has no prefab or the word has changed since it's prefab was compiled, the
+    /// prefab is compiled first by using the prefab rule.
+    /// Returns `None` if no such word was found in the encyclopedia or if no prefab rule has
+    /// been set.
+    pub fn entity_from_prefab(&mut self, name: &str) -> Option<specs::Entity> {
+        let (id, rule) = match (self.enc.word_id(name), self.prefab_rule.clone()) {
+            (Some(id), Some(rule)) => (id, rule),
+            _ => return None,
+        };
+        let prefab = self.prefab_by_id(id, &*rule);
+
+        let mut builder = self.builder();
+        prefab.add_to(&mut builder);
+        let entity = builder.finish();
+
+        self.spawned.insert(entity,
+                            Spawned {
+                                word: id,
+                                modifiers: Vec::new(),
+                                tags: Vec::new(),
+                                revision: prefab.get_revision(),
+                                comps: SpawnedComps::Prefab(prefab),
+                            });
+        Some(entity)
+    }
+
+    /// Returns the cached prefab of the word with the given name if one exists.
+    ///
+    /// The prefab might be out of date if the word has changed after it was compiled.
+    pub fn get_prefab(&self, name: &str) -> Option<&Prefab<C>> {
+        self.enc.word_id(name).and_then(|id| self.prefabs.get(&id)).map(|p| &**p)
+    }
+
+    /// Removes the cached prefab of the word with the given name.
+    pub fn invalidate_prefab(&mut self, name: &str) {
+        if let Some(id) = self.enc.word_id(name) {
+            self.prefabs.remove(&id);
+        }
+    }
+
+    /// Removes all of the cached prefabs.
+    ///
+    /// The changes of the words and the prefab rule are detected automatically, so this is
+    /// only needed for freeing the memory of the prefabs.
+    pub fn clear_prefabs(&mut self) {
+        self.prefabs.clear();
+    }
+
+    /// Updates the entities spawned from the word with the given name to match the current
+    /// version of the word.
+    ///
+    /// The entities spawned with `entity_from_prefab`, `spawn_with` and `entity_from_word` are
+    /// tracked, and the entities spawned with modifiers are updated when their modifier words
+    /// change too.
+    /// A prefab is compiled from the current version of the word with the prefab rule, and the
+    /// components of every affected entity are changed to match it.
+    /// Components the new prefab adds are inserted, components it drops are removed and changed
+    /// values are overwritten.
+    /// The entities built with an `EncEntityBuilder` are compared to a prefab compiled with the
+    /// prefab rule from the word they were built from, so the prefab rule has to add the same
+    /// components as the rule function used to build them.
+    /// The `policy` function is asked about every component change and the change is skipped if
+    /// it returns false, see `Prefab::reconcile`.
+    ///
+    /// Returns the amount of reconciled entities.
+    /// If the word or a modifier has been removed from the encyclopedia or if no prefab rule
+    /// has been set, the entities are left untouched. The entities that have been deleted from
+    /// the `specs::World` are forgotten.
+    pub fn reconcile_word<P>(&mut self, name: &str, policy: &P) -> usize
+        where P: Fn(&C, CompChange) -> bool
+    {
+        let (id, rule) = match (self.enc.word_id(name), self.prefab_rule.clone()) {
+            (Some(id), Some(rule)) => (id, rule),
+            _ => return 0,
+        };
+        let entities: Vec<specs::Entity> = self.spawned
+            .iter()
+            .filter(|&(_, s)| s.word == id || s.modifiers.contains(&id))
+            .map(|(&entity, _)| entity)
+            .collect();
+        let count = entities.into_iter()
+            .filter(|&entity| self.reconcile_entity(entity, &*rule, policy))
+            .count();
+        self.forget_unused_snapshots();
+        count
+    }
+
+    /// Reconciles the entities of every changed word, see `reconcile_word`.
+    ///
+    /// Returns the amount of reconciled entities.
+    pub fn reconcile_all<P>(&mut self, policy: &P) -> usize
+        where P: Fn(&C, CompChange) -> bool
+    {
+        let rule = match self.prefab_rule.clone() {
+            Some(rule) => rule,
+            None => return 0,
+        };
+        self.forget_dead_entities();
+        let mut entities: Vec<specs::Entity> = self.spawned
+            .iter()
+            .filter(|&(_, s)| {
+                self.spawned_revision(s.word, &s.modifiers).map_or(false, |rev| rev != s.revision)
+            })
+            .map(|(&entity, _)| entity)
+            .collect();
+        // the words are compiled in the same order every time
+        entities.sort();
+
+        let count = entities.into_iter()
+            .filter(|&entity| self.reconcile_entity(entity, &*rule, policy))
+            .count();
+        self.forget_unused_snapshots();
+        count
+    }
+
+    /// Reconciles a tracked entity if it's word or modifiers have changed.
+    /// Returns true if the entity was changed.
+    fn reconcile_entity<F, P>(&mut self, entity: specs::Entity, f: &F, policy: &P) -> bool
+        where F: ?Sized + Fn(GenericWord<U>, &mut PrefabBuilder<C>),
+              P: Fn(&C, CompChange) -> bool
+    {
+        if !self.ecs.is_alive(entity) {
+            self.spawned.remove(&entity);
+            return false;
+        }
+        let (new_prefab, revision) = {
+            let spawned = match self.spawned.get(&entity) {
+                Some(spawned) => spawned,
+                None => return false,
+            };
+            let revision = match self.spawned_revision(spawned.word, &spawned.modifiers) {
+                Some(revision) if revision != spawned.revision => revision,
+                _ => return false,
+            };
+            if spawned.modifiers.is_empty() && spawned.tags.is_empty() {
+                (None, revision)
+            } else {
+                let word = self.modified_word(spawned.word, &spawned.modifiers, &spawned.tags);
+                (Some(Rc::new(compile(word.unwrap(), revision, f))), revision)
+            }
+        };
+        let word = self.spawned[&entity].word;
+        // the prefabs of the words without modifiers are cached
+        let new_prefab = match new_prefab {
+            Some(prefab) => prefab,
+            None => self.prefab_by_id(word, f),
+        };
+
+        let spawned = self.spawned.get_mut(&entity).unwrap();
+        let old_prefab = match spawned.comps {
+            SpawnedComps::Prefab(ref prefab) => prefab.clone(),
+            SpawnedComps::Words(ref words) => {
+                Rc::new(compile(apply_modifiers(words, &spawned.tags), spawned.revision, f))
+            }
+        };
+        new_prefab.reconcile(&old_prefab, &self.ecs, entity, policy);
+        spawned.revision = revision;
+        spawned.comps = SpawnedComps::Prefab(new_prefab);
+        true
+    }
+}
+
+/// Compiles a prefab from the word by using the rule function `f`.
+fn compile<C, U, F>(word: GenericWord<U>, revision: u64, f: &F) -> Prefab<C>
+    where C: EncSysType + 'static,
+          U: UserTag,
+          F: ?Sized + Fn(GenericWord<U>, &mut PrefabBuilder<C>)
+{
+    let mut builder = PrefabBuilder::new();
+    f(word, &mut builder);
+    builder.finish(revision)
+}
+
+/// Returns the first word after applying the other words and then the tags to it as modifiers.
+fn apply_modifiers<U: UserTag>(words: &[Rc<GenericWord<'static, U>>],
+                               tags: &[LingTag<U>])
+                               -> GenericWord<'static, U> {
+    let mut word = (*words[0]).clone();
+    for modifier in &words[1..] {
+        word.apply_modifier(modifier);
+    }
+    if !tags.is_empty() {
+        word.apply_modifier(&GenericWord::new_from_collection("", tags));
+    }
+    word
 }
 
 /// Can create an entity from a word.
 pub trait WordToEntity {
     type CompName;
+    /// The type of the user-defined tags of the words.
+    type Tag: UserTag;
 
     /// Creates and stores an entity based on a word by using the function `f` and returns the
     /// created `specs::Entity` value.
-    fn entity_from_word<F>(&mut self, word: Word, f: &F) -> specs::Entity
-        where F: Fn(Word, &mut EncEntityBuilder<Self::CompName>);
+    fn entity_from_word<F>(&mut self, word: GenericWord<Self::Tag>, f: &F) -> specs::Entity
+        where F: Fn(GenericWord<Self::Tag>, &mut EncEntityBuilder<Self::CompName>);
+
+    /// Like `entity_from_word`, but applies the tags of the modifier words to the word before
+    /// running the function `f`.
+    ///
+    /// The modifiers are applied in order with `Word::apply_modifier`, so the later modifiers
+    /// take precedence over the earlier ones.
+    fn entity_from_word_with<F>(&mut self,
+                                word: GenericWord<Self::Tag>,
+                                modifiers: &[GenericWord<Self::Tag>],
+                                f: &F)
+                                -> specs::Entity
+        where F: Fn(GenericWord<Self::Tag>, &mut EncEntityBuilder<Self::CompName>)
+    {
+        let mut word = word;
+        for modifier in modifiers {
+            word.apply_modifier(modifier);
+        }
+        self.entity_from_word(word, f)
+    }
 }
 
-impl<C: EncSysType> WordToEntity for EncSysWorld<C> {
+impl<C: EncSysType, U: UserTag> WordToEntity for EncSysWorld<C, U> {
     type CompName = C;
+    type Tag = U;
 
-    fn entity_from_word<F>(&mut self, word: Word, f: &F) -> specs::Entity
-        where F: Fn(Word, &mut EncEntityBuilder<C>)
+    /// If the word is stored in the encyclopedia, the entity is tracked for reconciling with
+    /// the tags it has that the stored word doesn't and the other way around, see
+    /// `EncSysWorld::reconcile_word`.
+    fn entity_from_word<F>(&mut self, word: GenericWord<U>, f: &F) -> specs::Entity
+        where F: Fn(GenericWord<U>, &mut EncEntityBuilder<C>)
     {
-        // here is the builder that will construct the entity
-        let mut builder = self.builder();
-        // magic happens here
-        f(word, &mut builder);
-        // return the created entity
-        builder.finish()
+        let stored = self.enc.word_id(word.get_name()).map(|id| (id, self.snapshot(id).unwrap()));
+        let spawned = stored.map(|(id, stored)| {
+            // the differences from the stored word are applied like a modifier
+            let mut tags: Vec<LingTag<U>> = stored.get_tags()
+                .iter()
+                .filter(|t| !word.get_tags().contains(*t))
+                .map(|t| LingTag::Remove(Box::new(t.clone().into_owned())))
+                .collect();
+            tags.extend(word.get_tags()
+                .iter()
+                .filter(|t| !stored.get_tags().contains(*t))
+                .map(|t| t.clone().into_owned()));
+            Spawned {
+                word: id,
+                modifiers: Vec::new(),
+                tags: tags,
+                revision: self.enc.word_revision_by_id(id).unwrap(),
+                comps: SpawnedComps::Words(vec![stored]),
+            }
+        });
+        let entity = self.build_entity(word, f);
+        if let Some(spawned) = spawned {
+            self.spawned.insert(entity, spawned);
+        }
+        entity
     }
 }
 
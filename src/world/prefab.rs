@@ -0,0 +1,177 @@
+//! Contains the `Prefab` struct for caching the components created from a word.
+use std::any::Any;
+
+use world::specs;
+use world::EncEntityBuilder;
+
+use util::EncSysType;
+
+/// A component value with a component name that can be added to entities many times.
+trait PrefabComp<C: EncSysType> {
+    /// Returns the component name.
+    fn comp_name(&self) -> &C;
+
+    /// Adds a copy of the component to the entity being built.
+    fn add_to(&self, builder: &mut EncEntityBuilder<C>);
+
+    /// Inserts a copy of the component to an existing entity, replacing the old value if any.
+    fn insert_into(&self, ecs: &specs::World<C>, entity: specs::Entity);
+
+    /// Removes this kind of component from an existing entity.
+    fn remove_from(&self, ecs: &specs::World<C>, entity: specs::Entity);
+
+    /// Returns true if the other component has the same type and value as this one.
+    fn value_eq(&self, other: &dyn PrefabComp<C>) -> bool;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// A component value and it's component name.
+struct CompEntry<C, T> {
+    comp_name: C,
+    value: T,
+}
+
+impl<C, T> PrefabComp<C> for CompEntry<C, T>
+    where C: EncSysType + 'static,
+          T: specs::Component + Clone + PartialEq
+{
+    fn comp_name(&self) -> &C {
+        &self.comp_name
+    }
+
+    fn add_to(&self, builder: &mut EncEntityBuilder<C>) {
+        builder.add_comp(self.comp_name.clone(), self.value.clone());
+    }
+
+    fn insert_into(&self, ecs: &specs::World<C>, entity: specs::Entity) {
+        ecs.write_w_comp_id::<T>(self.comp_name.clone()).insert(entity, self.value.clone());
+    }
+
+    fn remove_from(&self, ecs: &specs::World<C>, entity: specs::Entity) {
+        ecs.write_w_comp_id::<T>(self.comp_name.clone()).remove(entity);
+    }
+
+    fn value_eq(&self, other: &dyn PrefabComp<C>) -> bool {
+        match other.as_any().downcast_ref::<CompEntry<C, T>>() {
+            Some(other) => self.value == other.value,
+            None => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A kind of change done to a component of an entity when it's reconciled with a new prefab.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CompChange {
+    /// The new prefab has a component the old one didn't have.
+    Added,
+    /// The new prefab doesn't have a component the old one had.
+    Removed,
+    /// The value of the component is different in the new prefab.
+    Changed,
+}
+
+/// A precompiled list of components created from a word by a rule function.
+///
+/// Spawning an entity from a prefab just clones it's components, so the rule function doesn't
+/// have to be run again.
+pub struct Prefab<C: EncSysType> {
+    /// The `Encyclopedia` revision of the word this prefab was compiled from.
+    revision: u64,
+    comps: Vec<Box<dyn PrefabComp<C>>>,
+}
+
+impl<C: EncSysType> Prefab<C> {
+    /// Returns the revision of the word this prefab was compiled from.
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns the amount of components in this prefab.
+    pub fn comp_amount(&self) -> usize {
+        self.comps.len()
+    }
+
+    /// Adds all of the components of this prefab to the entity being built.
+    pub fn add_to(&self, builder: &mut EncEntityBuilder<C>) {
+        for comp in &self.comps {
+            comp.add_to(builder);
+        }
+    }
+
+    fn get_comp(&self, comp_name: &C) -> Option<&dyn PrefabComp<C>> {
+        self.comps.iter().find(|c| c.comp_name() == comp_name).map(|c| &**c)
+    }
+
+    /// Changes the components of an entity spawned from the prefab `old` to match this prefab.
+    ///
+    /// Components only in this prefab are inserted, components only in `old` are removed and
+    /// components whose values differ between the prefabs are overwritten.
+    /// The `policy` function is asked about every change and the change is skipped if it returns
+    /// false, which can be used to preserve runtime state like the current hit points.
+    pub fn reconcile<P>(&self,
+                        old: &Prefab<C>,
+                        ecs: &specs::World<C>,
+                        entity: specs::Entity,
+                        policy: &P)
+        where P: Fn(&C, CompChange) -> bool
+    {
+        for comp in &self.comps {
+            let change = match old.get_comp(comp.comp_name()) {
+                None => CompChange::Added,
+                Some(old_comp) if !comp.value_eq(old_comp) => CompChange::Changed,
+                Some(_) => continue,
+            };
+            if policy(comp.comp_name(), change) {
+                comp.insert_into(ecs, entity);
+            }
+        }
+
+        for old_comp in &old.comps {
+            if self.get_comp(old_comp.comp_name()).is_none() &&
+               policy(old_comp.comp_name(), CompChange::Removed) {
+                old_comp.remove_from(ecs, entity);
+            }
+        }
+    }
+}
+
+/// Collects the components of a prefab.
+///
+/// This is given to the rule function instead of an `EncEntityBuilder` when compiling prefabs.
+pub struct PrefabBuilder<C: EncSysType> {
+    comps: Vec<Box<dyn PrefabComp<C>>>,
+}
+
+impl<C: EncSysType + 'static> PrefabBuilder<C> {
+    /// Creates a new empty prefab builder.
+    pub fn new() -> Self {
+        PrefabBuilder { comps: Vec::new() }
+    }
+
+    /// Adds a component with the name CompName, type T and data value to the prefab.
+    ///
+    /// The value is cloned for every entity spawned from the prefab.
+    /// Like with `EncEntityBuilder::add_comp`, the component has to be registered beforehand or
+    /// spawning will panic.
+    /// The values are compared when reconciling entities with a changed prefab.
+    pub fn add_comp<T: specs::Component + Clone + PartialEq>(&mut self, comp_name: C, value: T) {
+        self.comps.push(Box::new(CompEntry {
+            comp_name: comp_name,
+            value: value,
+        }));
+    }
+
+    /// Finishes the prefab building.
+    /// `revision` is the revision of the word the prefab was compiled from.
+    pub fn finish(self, revision: u64) -> Prefab<C> {
+        Prefab {
+            revision: revision,
+            comps: self.comps,
+        }
+    }
+}
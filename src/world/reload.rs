@@ -0,0 +1,204 @@
+//! Contains the hot-reloading of encyclopedia source files for `EncSysWorld`.
+//!
+//! The source format can't express the `LingTag::User` tags, so only the worlds without
+//! user-defined tags can watch source files.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use util::EncSysType;
+use enc::Word;
+use enc::ling::{LingTag, LingError};
+use enc::format::source;
+use world::EncSysWorld;
+
+/// An encyclopedia source file watched for changes.
+pub struct WatchedFile {
+    /// The modification time of the file when it was last loaded.
+    modified: Option<SystemTime>,
+    /// The words loaded from the file.
+    words: HashMap<String, HashSet<LingTag>>,
+    /// True if the modification time couldn't be read at the last poll, so the error isn't
+    /// reported again.
+    unreadable: bool,
+}
+
+impl WatchedFile {
+    fn new() -> Self {
+        WatchedFile {
+            modified: None,
+            words: HashMap::new(),
+            unreadable: false,
+        }
+    }
+}
+
+/// Tells which words were changed by reloading encyclopedia source files.
+///
+/// The names of the words are sorted.
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    /// The words that were added to the encyclopedia.
+    pub added: Vec<String>,
+    /// The words whose tags were changed.
+    pub changed: Vec<String>,
+    /// The words that were removed from the encyclopedia.
+    pub removed: Vec<String>,
+    /// The files that couldn't be loaded and the reasons why.
+    /// The words of these files are left as they were before the reload.
+    pub errors: Vec<(PathBuf, LingError)>,
+}
+
+impl ReloadReport {
+    /// Returns true if nothing was changed and no errors happened.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty() &&
+        self.errors.is_empty()
+    }
+
+    fn sort(&mut self) {
+        self.added.sort();
+        self.changed.sort();
+        self.removed.sort();
+    }
+}
+
+impl<C: EncSysType> EncSysWorld<C> {
+    /// Starts watching an encyclopedia source file.
+    ///
+    /// The words of the file are loaded on the next call to `poll_files`.
+    pub fn watch_file<P: Into<PathBuf>>(&mut self, path: P) {
+        self.watched.entry(path.into()).or_insert_with(WatchedFile::new);
+    }
+
+    /// Stops watching an encyclopedia source file.
+    /// The words loaded from the file are left in the encyclopedia.
+    pub fn unwatch_file<P: AsRef<Path>>(&mut self, path: P) {
+        self.watched.remove(path.as_ref());
+    }
+
+    /// Reloads every watched file whose modification time has changed since it was last loaded.
+    ///
+    /// The loaded words are completed and checked with `Encyclopedia::apply_schema`. If the
+    /// encyclopedia is tracking provenance, the file and the lines of the tags are recorded.
+    /// A word belongs to the watched file it was first loaded from, and the other watched files
+    /// can't define it until that file stops defining it or isn't watched anymore.
+    /// Files that can't be read or parsed, that have invalid words or that define the words of
+    /// other watched files are reported in the returned report and their words are left as they
+    /// were. Such a file is reported once, and it is loaded again when it is modified.
+    /// The words of a deleted file are removed, and the file is loaded again if it is created
+    /// again. A file whose modification time can't be read for another reason is reported once
+    /// until it can be read again.
+    /// The changed files are loaded in the order of their paths.
+    pub fn poll_files(&mut self) -> ReloadReport {
+        let mut report = ReloadReport::default();
+
+        let mut changed = Vec::new();
+        let mut deleted = Vec::new();
+        for (path, file) in &mut self.watched {
+            match fs::metadata(path).and_then(|m| m.modified()) {
+                Ok(time) => {
+                    file.unreadable = false;
+                    if file.modified != Some(time) {
+                        changed.push((path.clone(), time));
+                    }
+                }
+                Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                    file.unreadable = false;
+                    if file.modified.is_some() || !file.words.is_empty() {
+                        deleted.push(path.clone());
+                    }
+                }
+                Err(e) => {
+                    if !file.unreadable {
+                        file.unreadable = true;
+                        report.errors.push((path.clone(), e.into()));
+                    }
+                }
+            }
+        }
+        changed.sort();
+
+        for path in deleted {
+            let file = self.watched.get_mut(&path).unwrap();
+            file.modified = None;
+            for (name, _) in file.words.drain() {
+                self.enc.remove_word(&name as &str);
+                report.removed.push(name);
+            }
+        }
+        for (path, time) in changed {
+            self.reload_file_into(&path, &mut report);
+            self.watched.get_mut(&path).unwrap().modified = Some(time);
+        }
+        report.sort();
+        report
+    }
+
+    /// Reloads a watched file even if it hasn't been modified.
+    /// Does nothing if the file isn't watched.
+    pub fn reload_file<P: AsRef<Path>>(&mut self, path: P) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        self.reload_file_into(path.as_ref(), &mut report);
+        report.sort();
+        report
+    }
+
+    /// Reloads a watched file and applies the changed words to the encyclopedia.
+    fn reload_file_into(&mut self, path: &Path, report: &mut ReloadReport) {
+        if !self.watched.contains_key(path) {
+            return;
+        }
+        let mut source_words = match source::read_source_words(path) {
+            Ok(words) => words,
+            Err(e) => {
+                report.errors.push((path.to_owned(), e));
+                return;
+            }
+        };
+        for source_word in &mut source_words {
+            let word = &mut source_word.word;
+            if let Err(e) = self.enc.apply_schema(word) {
+                let name = word.get_name().into_owned();
+                report.errors.push((path.to_owned(), LingError::InvalidWord(name, Box::new(e))));
+                return;
+            }
+        }
+        let new_words: HashMap<String, HashSet<LingTag>> =
+            source_words.iter().map(|w| w.word.clone().into_map_entry()).collect();
+
+        let mut owned: Vec<&String> = new_words.keys()
+            .filter(|name| {
+                self.watched.iter().any(|(p, f)| p != path && f.words.contains_key(*name))
+            })
+            .collect();
+        owned.sort();
+        if let Some(name) = owned.first() {
+            report.errors.push((path.to_owned(), LingError::WordExists((*name).clone())));
+            return;
+        }
+
+        let file = self.watched.get_mut(path).unwrap();
+
+        for name in file.words.keys() {
+            if !new_words.contains_key(name) {
+                self.enc.remove_word(name as &str);
+                report.removed.push(name.clone());
+            }
+        }
+
+        for (name, tags) in &new_words {
+            match file.words.get(name) {
+                Some(old_tags) if old_tags == tags => continue,
+                Some(_) => report.changed.push(name.clone()),
+                None => report.added.push(name.clone()),
+            }
+            self.enc.add_word(Word::new_from_collection(name as &str, tags));
+        }
+
+        file.words = new_words;
+        source::record_provenance(&mut self.enc, path, &source_words);
+    }
+}
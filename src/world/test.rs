@@ -1,12 +1,17 @@
 extern crate specs;
 
+use std::{env, fs, process};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
 use super::*;
 use enc::*;
 use enc::ling::LingTag;
 
 type ESMan = EncSysWorld<String>;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 struct IntComp(i32);
 
 impl specs::Component for IntComp {
@@ -34,3 +39,222 @@ fn ent_from_word() {
     assert_eq!(man.ecs.read_w_comp_id::<IntComp>("comp".to_owned()).get(ent),
                Some(&IntComp(1)));
 }
+
+#[test]
+fn ent_from_prefab() {
+    let mut man: ESMan = ESMan::new();
+    man.enc.add_word(Word::new_from_collection("word1", vec![LingTag::Custom("tag1".to_owned())]));
+    man.ecs.register_w_comp_id::<IntComp>("comp".to_owned());
+
+    // nothing is spawned without a prefab rule
+    assert_eq!(man.entity_from_prefab("word1"), None);
+    man.set_prefab_rule(|word: Word, builder: &mut PrefabBuilder<String>| {
+        let value = if word.has_tag(&LingTag::Custom("tag1".to_owned())) { 1 } else { 2 };
+        builder.add_comp::<IntComp>("comp".to_owned(), IntComp(value));
+    });
+
+    let ent1 = man.entity_from_prefab("word1").unwrap();
+    let ent2 = man.entity_from_prefab("word1").unwrap();
+    assert!(ent1 != ent2);
+    assert_eq!(man.get_prefab("word1").unwrap().comp_amount(), 1);
+
+    // changing the word recompiles the prefab
+    man.enc.add_word(Word::new("word1"));
+    let ent3 = man.entity_from_prefab("word1").unwrap();
+
+    // replacing the rule recompiles the prefab
+    man.set_prefab_rule(|_: Word, builder: &mut PrefabBuilder<String>| {
+        builder.add_comp::<IntComp>("comp".to_owned(), IntComp(3));
+    });
+    assert!(man.get_prefab("word1").is_none());
+    let ent4 = man.entity_from_prefab("word1").unwrap();
+
+    {
+        let comps = man.ecs.read_w_comp_id::<IntComp>("comp".to_owned());
+        assert_eq!(comps.get(ent1), Some(&IntComp(1)));
+        assert_eq!(comps.get(ent2), Some(&IntComp(1)));
+        assert_eq!(comps.get(ent3), Some(&IntComp(2)));
+        assert_eq!(comps.get(ent4), Some(&IntComp(3)));
+    }
+
+    man.enc.remove_word("word1");
+    assert_eq!(man.entity_from_prefab("word1"), None);
+    assert!(man.get_prefab("word1").is_none());
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct NameComp(String);
+
+impl specs::Component for NameComp {
+    type Storage = specs::VecStorage<Self>;
+}
+
+#[test]
+fn reconcile_entities() {
+    let mut man: ESMan = ESMan::new();
+    man.ecs.register_w_comp_id::<IntComp>("hp".to_owned());
+    man.ecs.register_w_comp_id::<IntComp>("max_hp".to_owned());
+    man.ecs.register_w_comp_id::<NameComp>("name".to_owned());
+
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+    man.enc.add_word(Word::new_from_collection("goblin", vec![tag("named")]));
+
+    man.set_prefab_rule(|word: Word, builder: &mut PrefabBuilder<String>| {
+        let hp = if word.has_tag(&LingTag::Custom("tough".to_owned())) { 20 } else { 10 };
+        builder.add_comp::<IntComp>("hp".to_owned(), IntComp(hp));
+        builder.add_comp::<IntComp>("max_hp".to_owned(), IntComp(hp));
+        if word.has_tag(&LingTag::Custom("named".to_owned())) {
+            builder.add_comp::<NameComp>("name".to_owned(), NameComp(word.get_name().into_owned()));
+        }
+    });
+    // keep the current hit points
+    let policy = |comp: &String, change: CompChange| comp != "hp" || change != CompChange::Changed;
+
+    let ent = man.entity_from_prefab("goblin").unwrap();
+    man.ecs.write_w_comp_id::<IntComp>("hp".to_owned()).insert(ent, IntComp(3));
+
+    man.enc.add_word(Word::new_from_collection("goblin", vec![tag("tough")]));
+    assert_eq!(man.reconcile_all(&policy), 1);
+    // nothing changed since the last reconciliation
+    assert_eq!(man.reconcile_all(&policy), 0);
+
+    assert_eq!(man.ecs.read_w_comp_id::<IntComp>("hp".to_owned()).get(ent),
+               Some(&IntComp(3)));
+    assert_eq!(man.ecs.read_w_comp_id::<IntComp>("max_hp".to_owned()).get(ent),
+               Some(&IntComp(20)));
+    assert_eq!(man.ecs.read_w_comp_id::<NameComp>("name".to_owned()).get(ent), None);
+    assert_eq!(man.get_spawned_word(ent), man.enc.word_id("goblin"));
+}
+
+#[test]
+fn reconcile_modified_entities() {
+    let mut man: ESMan = ESMan::new();
+    man.ecs.register_w_comp_id::<IntComp>("damage".to_owned());
+
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+    man.enc.add_word(Word::new_from_collection("sword", vec![tag("sharp")]));
+    man.enc.add_word(Word::new_from_collection("rusty", vec![tag("rusty")]));
+
+    let damage = |word: &Word| {
+        let sharp = if word.has_tag(&LingTag::Custom("sharp".to_owned())) { 5 } else { 1 };
+        let rusty = if word.has_tag(&LingTag::Custom("rusty".to_owned())) { 2 } else { 0 };
+        sharp - rusty
+    };
+    let build = |word: Word, builder: &mut EncEntityBuilder<String>| {
+        builder.add_comp::<IntComp>("damage".to_owned(), IntComp(damage(&word)));
+    };
+    man.set_prefab_rule(move |word: Word, builder: &mut PrefabBuilder<String>| {
+        builder.add_comp::<IntComp>("damage".to_owned(), IntComp(damage(&word)));
+    });
+    let policy = |_: &String, _: CompChange| true;
+
+    let ent1 = man.spawn_with("sword", &["rusty"], &build).unwrap();
+    let word = man.enc.get_word("sword").unwrap().into_owned();
+    let ent2 = man.entity_from_word(word, &build);
+    let ent3 = man.spawn_with("sword", &["rusty"], &build).unwrap();
+    man.ecs.delete_now(ent3);
+    // the entities share the versions of the words they were built from
+    assert_eq!(man.snapshots.len(), 2);
+
+    // changing the modifier changes only the modified entity
+    man.enc.add_word(Word::new_from_collection("rusty", vec![tag("rusty"), tag("old")]));
+    assert_eq!(man.reconcile_word("rusty", &policy), 1);
+    // the deleted entity was forgotten
+    assert_eq!(man.get_spawned_word(ent3), None);
+
+    man.enc.add_word(Word::new("sword"));
+    assert_eq!(man.reconcile_all(&policy), 2);
+    assert!(man.snapshots.is_empty());
+    let comps = man.ecs.read_w_comp_id::<IntComp>("damage".to_owned());
+    assert_eq!(comps.get(ent1), Some(&IntComp(-1)));
+    assert_eq!(comps.get(ent2), Some(&IntComp(1)));
+}
+
+#[test]
+fn ent_with_modifiers() {
+    let mut man: ESMan = ESMan::new();
+    man.ecs.register_w_comp_id::<IntComp>("comp".to_owned());
+
+    let tag = |s: &str| LingTag::Custom(s.to_owned());
+    man.enc.add_word(Word::new_from_collection("sword", vec![tag("metal"), tag("sharp")]));
+    man.enc.add_word(Word::new_from_collection("wooden",
+                                               vec![LingTag::Remove(Box::new(tag("metal"))),
+                                                    tag("wood")]));
+    man.enc.add_word(Word::new_from_collection("rusty", vec![tag("rusty")]));
+
+    // counts the tags of the modified word
+    let rule = |word: Word, builder: &mut EncEntityBuilder<String>| {
+        builder.add_comp::<IntComp>("comp".to_owned(), IntComp(word.tag_amount() as i32));
+    };
+
+    let ent1 = man.spawn_with("sword", &["rusty"], &rule).unwrap();
+    let ent2 = man.spawn_with("sword", &["wooden", "rusty"], &rule).unwrap();
+    assert!(man.spawn_with("sword", &["golden"], &rule).is_err());
+    // the extra tags are applied after the modifiers
+    let extra = vec![LingTag::Remove(Box::new(tag("rusty"))), tag("blessed"), tag("holy")];
+    let ent3 = man.spawn_with_tags("sword", &["rusty"], extra, &rule).unwrap();
+
+    let comps = man.ecs.read_w_comp_id::<IntComp>("comp".to_owned());
+    // metal, sharp and rusty
+    assert_eq!(comps.get(ent1), Some(&IntComp(3)));
+    // sharp, wood and rusty
+    assert_eq!(comps.get(ent2), Some(&IntComp(3)));
+    // metal, sharp, blessed and holy
+    assert_eq!(comps.get(ent3), Some(&IntComp(4)));
+}
+
+#[test]
+fn reload_files() {
+    let mut man: ESMan = ESMan::new();
+    let dir = env::temp_dir();
+    let path = dir.join(format!("encsys_reload_files_test_{}.enc", process::id()));
+    let other = dir.join(format!("encsys_reload_files_test_{}_other.enc", process::id()));
+
+    let write = |path: &PathBuf, src: &str| {
+        File::create(path).unwrap().write_all(src.as_bytes()).unwrap()
+    };
+
+    write(&path, "goblin:\n    Custom green\norc:\n");
+    man.watch_file(path.clone());
+    let report = man.poll_files();
+    assert_eq!(report.added.len(), 2);
+    assert_eq!(man.enc.word_amount(), 2);
+    // nothing has been modified
+    assert!(man.poll_files().is_empty());
+
+    write(&path, "goblin:\n    Custom red\ntroll:\n");
+    let report = man.reload_file(&path);
+    assert_eq!(report.added, vec!["troll".to_owned()]);
+    assert_eq!(report.changed, vec!["goblin".to_owned()]);
+    assert_eq!(report.removed, vec!["orc".to_owned()]);
+    assert!(man.enc.get_word("orc").is_none());
+
+    // parse errors keep the previous words
+    write(&path, "goblin:\n    Bogus tag\n");
+    let report = man.reload_file(&path);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.added.is_empty() && report.changed.is_empty() && report.removed.is_empty());
+    assert!(man.enc.get_word("goblin").unwrap().has_tag(&LingTag::Custom("red".to_owned())));
+    assert!(man.enc.get_word("troll").is_some());
+
+    // the words of another watched file can't be redefined
+    write(&other, "goblin:\n    Custom blue\n");
+    man.watch_file(other.clone());
+    let report = man.poll_files();
+    // the broken file and the other file are reported
+    assert_eq!(report.errors.len(), 2);
+    assert!(report.errors.iter().any(|e| e.0 == other));
+    assert!(man.enc.get_word("goblin").unwrap().has_tag(&LingTag::Custom("red".to_owned())));
+    // the failed files are reported only once
+    assert!(man.poll_files().is_empty());
+
+    // the words of a deleted file are removed
+    fs::remove_file(&path).unwrap();
+    let report = man.poll_files();
+    assert_eq!(report.removed, vec!["goblin".to_owned(), "troll".to_owned()]);
+    assert!(report.errors.is_empty());
+    assert_eq!(man.enc.word_amount(), 0);
+    assert!(man.poll_files().is_empty());
+
+    fs::remove_file(&other).unwrap();
+}